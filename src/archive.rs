@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Total uncompressed size an archive may extract to, against decompression bombs
+pub const MAX_EXTRACTED_SIZE: u64 = 1024 * 1024 * 1024; // 1GB
+
+enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+fn format(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else {
+        None
+    }
+}
+
+/// Whether `input` is an archive file that can be extracted
+pub fn is_archive(input: &str) -> bool {
+    let path = Path::new(input);
+    format(path).is_some() && path.is_file()
+}
+
+/// Archive name without its extension, used to label the input
+pub fn archive_name(input: &str) -> String {
+    let name = Path::new(input)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let lower = name.to_ascii_lowercase();
+    [".tar.gz", ".tgz", ".tar", ".zip"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map_or(name.clone(), |ext| {
+            name[..name.len() - ext.len()].to_string()
+        })
+}
+
+/// Extracts an archive into a temporary directory, failing once more than
+/// `max_size` bytes would be written
+pub fn extract(path: &Path, max_size: u64) -> Result<TempDir> {
+    let format =
+        format(path).with_context(|| format!("{} is not a supported archive", path.display()))?;
+    let temp_dir = TempDir::new()?;
+    info!(
+        "Extracting {} into {}",
+        path.display(),
+        temp_dir.path().display()
+    );
+
+    let file =
+        File::open(path).with_context(|| format!("Failed to open archive {}", path.display()))?;
+    let reader = BufReader::new(file);
+    match format {
+        Format::Tar => extract_tar(reader, temp_dir.path(), max_size),
+        Format::TarGz => extract_tar(GzDecoder::new(reader), temp_dir.path(), max_size),
+        Format::Zip => extract_zip(reader, temp_dir.path(), max_size),
+    }
+    .with_context(|| format!("Failed to extract {}", path.display()))?;
+
+    Ok(temp_dir)
+}
+
+/// Directory to traverse in an extracted archive: the single top-level
+/// directory that snapshots such as GitHub tarballs wrap everything in, or
+/// the extraction directory itself
+pub fn content_root(dir: &Path) -> PathBuf {
+    let entries: Vec<_> = std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).collect())
+        .unwrap_or_default();
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => entry.path(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+fn size_error(max_size: u64) -> anyhow::Error {
+    anyhow::anyhow!("archive expands to more than {max_size} bytes")
+}
+
+fn extract_tar(reader: impl Read, target: &Path, max_size: u64) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    let mut total: u64 = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        // Entries are unpacked with exactly the size from their header
+        total += entry.header().size()?;
+        if total > max_size {
+            return Err(size_error(max_size));
+        }
+        // Entries escaping the target (`..`, absolute paths) are skipped
+        if !entry.unpack_in(target)? {
+            warn!("Skipping unsafe archive entry {}", entry.path()?.display());
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip(reader: impl Read + io::Seek, target: &Path, max_size: u64) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut total: u64 = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let Some(relative) = entry.enclosed_name() else {
+            warn!(
+                "Skipping unsafe archive entry {}",
+                entry.name().unwrap_or_default()
+            );
+            continue;
+        };
+        let path = target.join(relative);
+
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Declared sizes can lie, so count what is actually written
+        let remaining = max_size - total;
+        let written = io::copy(
+            &mut (&mut entry).take(remaining + 1),
+            &mut File::create(&path)?,
+        )?;
+        total += written;
+        if total > max_size {
+            return Err(size_error(max_size));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn write_tar_gz(path: &Path, files: &[(&str, &str)]) -> Result<()> {
+        let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, content.as_bytes())?;
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tar_gz() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("repo-main.tar.gz");
+        write_tar_gz(
+            &archive,
+            &[
+                ("repo-main/src/lib.rs", "pub fn lib() {}"),
+                ("repo-main/README.md", "# Repo"),
+            ],
+        )?;
+
+        assert!(is_archive(archive.to_str().unwrap()));
+        assert_eq!(archive_name(archive.to_str().unwrap()), "repo-main");
+
+        let extracted = extract(&archive, MAX_EXTRACTED_SIZE)?;
+        let root = content_root(extracted.path());
+        assert!(root.ends_with("repo-main"));
+        assert_eq!(
+            std::fs::read_to_string(root.join("src/lib.rs"))?,
+            "pub fn lib() {}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_zip() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("snapshot.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive)?);
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("main.rs", options)?;
+        writer.write_all(b"fn main() {}")?;
+        writer.start_file("docs/guide.md", options)?;
+        writer.write_all(b"# Guide")?;
+        writer.finish()?;
+
+        let extracted = extract(&archive, MAX_EXTRACTED_SIZE)?;
+        // Several top-level entries: the extraction directory is the root
+        assert_eq!(content_root(extracted.path()), extracted.path());
+        assert_eq!(
+            std::fs::read_to_string(extracted.path().join("docs/guide.md"))?,
+            "# Guide"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_size_cap() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("bomb.tgz");
+        let big = "a".repeat(4096);
+        write_tar_gz(&archive, &[("one.txt", &big), ("two.txt", &big)])?;
+
+        let error = extract(&archive, 6000).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("more than 6000 bytes"),
+            "{error:#}"
+        );
+
+        Ok(())
+    }
+}
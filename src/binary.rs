@@ -0,0 +1,131 @@
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use std::fmt::Write;
+use std::path::Path;
+
+/// How files detected as binary are handled
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum BinaryMode {
+    /// Replace the file with a skip marker
+    #[default]
+    Skip,
+    /// Show the first bytes as an offset/hex/ASCII dump
+    Hexdump,
+    /// Include the whole file base64-encoded
+    Base64,
+}
+
+/// Renders bytes as a classic `hexdump -C` style dump, 16 bytes per line
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let _ = write!(output, "{:08x} ", line * 16);
+        for i in 0..16 {
+            if i == 8 {
+                output.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(output, " {byte:02x}");
+                }
+                None => output.push_str("   "),
+            }
+        }
+        output.push_str("  |");
+        output.extend(chunk.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        output.push_str("|\n");
+    }
+    output
+}
+
+/// Encodes bytes as base64 in lines of 76 characters, as in MIME
+pub fn base64_lines(bytes: &[u8]) -> String {
+    let encoded = BASE64_STANDARD.encode(bytes);
+    let mut output = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for (i, chunk) in encoded.as_bytes().chunks(76).enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        // Base64 output is ASCII, so every chunk is valid UTF-8
+        output.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+    }
+    output
+}
+
+/// Best-effort MIME type from the file's magic bytes, then its extension
+pub fn guess_mime(path: &Path, bytes: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x00asm", "application/wasm"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+    ];
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return mime;
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("ico") => "image/x-icon",
+        Some("bmp") => "image/bmp",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x03\x00>");
+        assert_eq!(
+            dump,
+            "00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|\n\
+             00000010  03 00 3e                                          |..>|\n"
+        );
+    }
+
+    #[test]
+    fn test_base64_lines() {
+        assert_eq!(base64_lines(b"gitmelt"), "Z2l0bWVsdA==");
+        let lines = base64_lines(&[0u8; 100]);
+        assert_eq!(lines.lines().map(str::len).collect::<Vec<_>>(), [76, 60]);
+    }
+
+    #[test]
+    fn test_guess_mime() {
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+        assert_eq!(guess_mime(Path::new("logo.bin"), png), "image/png");
+        assert_eq!(
+            guess_mime(Path::new("favicon.ICO"), b"\x00\x00\x01\x00"),
+            "image/x-icon"
+        );
+        assert_eq!(
+            guess_mime(Path::new("data"), b"\x00\x01"),
+            "application/octet-stream"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILENAME: &str = ".gitmelt.toml";
+
+/// Project defaults read from `.gitmelt.toml`. Every field is optional and
+/// explicit CLI flags take precedence over it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub preset: Option<String>,
+    pub prologue: Option<String>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub output: Option<PathBuf>,
+    pub output_name: Option<String>,
+    pub no_tokens: Option<bool>,
+    pub show_tokens: Option<bool>,
+    pub show_lines: Option<bool>,
+    pub token_model: Option<String>,
+    /// Markdown fence language overrides, e.g. `tsx = "typescript"`
+    pub lang_map: Option<HashMap<String, String>>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+}
+
+/// Looks for a config file in `start` and then in each of its ancestors
+pub fn discover(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILENAME))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discover_walks_up() -> Result<()> {
+        let dir = tempdir()?;
+        let nested = dir.path().join("a/b");
+        fs::create_dir_all(&nested)?;
+        fs::write(dir.path().join(CONFIG_FILENAME), "preset = \"markdown\"\n")?;
+
+        let found = discover(&nested).unwrap();
+        assert_eq!(found, dir.path().join(CONFIG_FILENAME));
+
+        let config = Config::load(&found)?;
+        assert_eq!(config.preset.as_deref(), Some("markdown"));
+        assert!(config.include.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_keys() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join(CONFIG_FILENAME);
+        fs::write(&path, "presett = \"markdown\"\n")?;
+
+        assert!(Config::load(&path).is_err());
+
+        Ok(())
+    }
+}
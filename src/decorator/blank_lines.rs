@@ -0,0 +1,51 @@
+use super::ContentDecorator;
+use std::path::Path;
+
+/// Wraps another decorator, collapsing runs of blank lines into one and
+/// trimming trailing whitespace before the inner decorator sees the content
+pub struct CollapseBlankLinesDecorator {
+    pub inner: Box<dyn ContentDecorator>,
+}
+
+fn collapse_blank_lines(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut previous_blank = false;
+
+    for line in content.lines() {
+        let line = line.trim_end();
+        let blank = line.is_empty();
+        if blank && previous_blank {
+            continue;
+        }
+        output.push_str(line);
+        output.push('\n');
+        previous_blank = blank;
+    }
+    if !content.ends_with('\n') {
+        output.pop();
+    }
+    output
+}
+
+impl ContentDecorator for CollapseBlankLinesDecorator {
+    fn transform(&self, path: &Path, content: String) -> String {
+        self.inner.transform(path, collapse_blank_lines(&content))
+    }
+
+    forward_decorator!(inner);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_blank_lines() {
+        assert_eq!(
+            collapse_blank_lines("a  \n\n\n  \nb\t\n\nc\n"),
+            "a\n\nb\n\nc\n"
+        );
+        assert_eq!(collapse_blank_lines("a\n\n\n\nb"), "a\n\nb");
+        assert_eq!(collapse_blank_lines(""), "");
+    }
+}
@@ -1,18 +1,67 @@
-use super::{ContentDecorator, format_path};
+use super::{ContentDecorator, FileInfo, format_path, header_suffix};
 use std::path::Path;
 
 /// The default decorator that mimics the original behavior
-pub struct DefaultDecorator;
+#[derive(Default)]
+pub struct DefaultDecorator {
+    /// Append the file's token count to its header
+    pub show_tokens: bool,
+    /// Append the file's line count to its header
+    pub show_lines: bool,
+    /// Replaces the banner above each file. Supports `{path}`, `{tokens}`,
+    /// `{lines}` and `{notes}` placeholders and `\n` for line breaks.
+    pub template: Option<String>,
+}
+
+/// Renders a `--separator-template` for one file in a single pass, so a path
+/// containing `{tokens}` or `\n` is written as it is
+fn render_template(template: &str, path: &str, info: &FileInfo) -> String {
+    let tokens = info.tokens.map(|t| t.to_string()).unwrap_or_default();
+    let lines = info.lines.map(|l| l.to_string()).unwrap_or_default();
+    let notes = header_suffix(false, false, info);
+    let substitutions = [
+        ("\\n", "\n"),
+        ("{path}", path),
+        ("{tokens}", &tokens),
+        ("{lines}", &lines),
+        ("{notes}", notes.trim_start()),
+    ];
+
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while !rest.is_empty() {
+        match substitutions
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                output.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                let next = rest.chars().next().map_or(1, char::len_utf8);
+                output.push_str(&rest[..next]);
+                rest = &rest[next..];
+            }
+        }
+    }
+    output
+}
 
 impl ContentDecorator for DefaultDecorator {
-    fn before(&self, path: &Path) -> Option<String> {
+    fn before(&self, path: &Path, info: &FileInfo) -> Option<String> {
         let path_str = format_path(path);
+        if let Some(template) = &self.template {
+            return Some(render_template(template, &path_str, info));
+        }
+
+        let suffix = header_suffix(self.show_tokens, self.show_lines, info);
         Some(format!(
-            "================================================\nFILE: {path_str}\n================================================\n"
+            "================================================\nFILE: {path_str}{suffix}\n================================================\n"
         ))
     }
 
-    fn after(&self, _path: &Path) -> Option<String> {
+    fn after(&self, _path: &Path, _info: &FileInfo) -> Option<String> {
         // The original implementation adds an empty line after content
         Some(String::new())
     }
@@ -21,3 +70,42 @@ impl ContentDecorator for DefaultDecorator {
         content
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separator_template() {
+        let decorator = DefaultDecorator {
+            template: Some("### {path} [{tokens}, {lines}]\\n{notes}".to_string()),
+            ..DefaultDecorator::default()
+        };
+        let info = FileInfo {
+            tokens: Some(42),
+            lines: Some(7),
+            notes: vec!["truncated".to_string()],
+        };
+
+        assert_eq!(
+            decorator.before(Path::new("./src/main.rs"), &info),
+            Some("### src/main.rs [42, 7]\n(truncated)".to_string())
+        );
+
+        // Substituted values are not expanded again
+        assert_eq!(
+            decorator.before(Path::new("{tokens}\\n.rs"), &FileInfo::default()),
+            Some("### {tokens}\\n.rs [, ]\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_default_banner() {
+        let before = DefaultDecorator::default()
+            .before(Path::new("src/main.rs"), &FileInfo::default())
+            .unwrap();
+
+        assert!(before.starts_with("====="));
+        assert!(before.contains("FILE: src/main.rs\n"));
+    }
+}
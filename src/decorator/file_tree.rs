@@ -1,37 +1,196 @@
-use super::{GlobalDecorator, PrologueMode, format_path};
+use super::{GlobalDecorator, PrologueMode, format_count, format_path};
 use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
+/// A traversal root shown in the prologue
+#[derive(Clone)]
+pub struct TreeRoot {
+    /// Name used to prefix this root's files when there are several roots
+    pub label: String,
+    pub path: PathBuf,
+}
+
 /// A global decorator that prints a file tree
 pub struct FileTreeDecorator {
-    pub root: PathBuf,
+    pub roots: Vec<TreeRoot>,
     pub mode: PrologueMode,
+    /// Annotate listed files with their size and line count, or in tree mode
+    /// every node with its size on disk, directories summing their files
+    pub stats: bool,
+    /// Files over this many bytes, which ingestion skips, are listed without
+    /// a line count; 0 means no limit
+    pub max_file_size: u64,
+}
+
+/// Formats a byte count for humans, e.g. `512 B` or `4.2 KB`
+#[allow(clippy::cast_precision_loss)]
+pub fn format_human_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Size in bytes and number of lines of a file. The size is zero if the file
+/// cannot be read; the line count is `None` then and for files over
+/// `max_file_size`, which are not read at all.
+fn file_stats(path: &Path, max_file_size: u64) -> (u64, Option<usize>) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return (0, None);
+    };
+    let bytes = metadata.len();
+    if max_file_size > 0 && bytes > max_file_size {
+        return (bytes, None);
+    }
+    (bytes, count_lines(path).ok())
+}
+
+/// Number of lines in a file, counting a final line without a newline
+fn count_lines(path: &Path) -> std::io::Result<usize> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let (mut lines, mut last) = (0, b'\n');
+    loop {
+        let buffer = reader.fill_buf()?;
+        let Some(&end) = buffer.last() else {
+            break;
+        };
+        lines += buffer.iter().filter(|&&b| b == b'\n').count();
+        last = end;
+        let read = buffer.len();
+        reader.consume(read);
+    }
+    Ok(lines + usize::from(last != b'\n'))
+}
+
+/// Path of a file relative to its root, prefixed with the root's label when
+/// there is more than one root so files from different roots stay distinct
+pub fn display_path(roots: &[TreeRoot], file: &Path) -> String {
+    let Some(root) = roots.iter().find(|root| file.starts_with(&root.path)) else {
+        return format_path(file);
+    };
+
+    let relative = format_path(file.strip_prefix(&root.path).unwrap_or(file));
+    // A file given directly as an input is its own root
+    if relative.is_empty() {
+        return if roots.len() > 1 {
+            format_path(Path::new(&root.label))
+        } else {
+            format_path(Path::new(file.file_name().unwrap_or(file.as_os_str())))
+        };
+    }
+    if roots.len() > 1 {
+        format!("{}/{relative}", format_path(Path::new(&root.label)))
+    } else {
+        relative
+    }
+}
+
+/// Top-level directory of a file within its root, e.g. `src/`, for grouping
+/// files. Files directly in the root belong to `./` (or `label/` with several roots).
+pub fn top_level_dir(roots: &[TreeRoot], file: &Path) -> String {
+    dir_at_depth(roots, file, 1)
+}
+
+/// Directory of a file within its root, cut to its first `depth` levels, e.g.
+/// `src/decorator/` for depth 2. Like [`top_level_dir`], which is depth 1.
+pub fn dir_at_depth(roots: &[TreeRoot], file: &Path, depth: usize) -> String {
+    let display = display_path(roots, file);
+    // With several roots the label is an extra leading level
+    let depth = depth + usize::from(roots.len() > 1);
+    let parts: Vec<&str> = display.split('/').collect();
+    let dirs = &parts[..parts.len() - 1];
+    if dirs.is_empty() {
+        if roots.len() > 1 {
+            format!("{}/", parts[0])
+        } else {
+            "./".to_string()
+        }
+    } else {
+        format!("{}/", dirs[..depth.min(dirs.len())].join("/"))
+    }
+}
+
+impl FileTreeDecorator {
+    fn display_path(&self, file: &Path) -> String {
+        display_path(&self.roots, file)
+    }
+
+    /// Flat list of the files, with their size and line count under `stats`
+    fn list_section(&self, files: &[PathBuf], stats: bool) -> String {
+        let mut output = String::new();
+        output.push_str("Files included in this digest:\n");
+        let (mut total_bytes, mut total_lines) = (0, 0);
+        for file in files {
+            if stats {
+                let (bytes, lines) = file_stats(file, self.max_file_size);
+                total_bytes += bytes;
+                total_lines += lines.unwrap_or(0);
+                let _ = write!(
+                    output,
+                    "- {} ({}",
+                    self.display_path(file),
+                    format_human_size(bytes)
+                );
+                if let Some(lines) = lines {
+                    let _ = write!(output, ", {} lines", format_count(lines));
+                }
+                output.push_str(")\n");
+            } else {
+                let _ = writeln!(output, "- {}", self.display_path(file));
+            }
+        }
+        if stats {
+            let _ = writeln!(
+                output,
+                "Total: {} files, {}, {} lines",
+                format_count(files.len()),
+                format_human_size(total_bytes),
+                format_count(total_lines)
+            );
+        }
+        output.push('\n');
+        output
+    }
+
+    /// Directory tree of the files, with sizes under `self.stats`
+    fn tree_section(&self, files: &[PathBuf]) -> String {
+        let mut output = String::new();
+        output.push_str("File structure:\n");
+        // Sizes come from metadata rather than token counts: the prologue
+        // is written before any file is processed, and sizing by tokens
+        // would mean reading and tokenizing every file twice
+        let tree = build_tree(files.iter().map(|file| {
+            let bytes = if self.stats {
+                std::fs::metadata(file).map_or(0, |metadata| metadata.len())
+            } else {
+                0
+            };
+            (self.display_path(file), bytes)
+        }));
+        print_tree(&tree, "", self.stats, &mut output);
+        output.push('\n');
+        output
+    }
 }
 
 impl GlobalDecorator for FileTreeDecorator {
     fn prologue(&self, files: &[PathBuf]) -> Option<String> {
         match self.mode {
             PrologueMode::Off => None,
-            PrologueMode::List => {
-                let mut output = String::new();
-                output.push_str("Files included in this digest:\n");
-                for file in files {
-                    // Try to make path relative to root for cleaner output
-                    let display_path = file.strip_prefix(&self.root).unwrap_or(file);
-                    let _ = writeln!(output, "- {}", format_path(display_path));
-                }
-                output.push('\n');
-                Some(output)
-            }
-            PrologueMode::Tree => {
-                let mut output = String::new();
-                output.push_str("File structure:\n");
-                let tree = build_tree(&self.root, files);
-                print_tree(&tree, "", &mut output);
-                output.push('\n');
-                Some(output)
-            }
+            PrologueMode::List => Some(self.list_section(files, self.stats)),
+            PrologueMode::Tree => Some(self.tree_section(files)),
+            // The list is there to copy from, so it always carries sizes
+            PrologueMode::Full => Some(self.tree_section(files) + &self.list_section(files, true)),
         }
     }
 }
@@ -40,23 +199,24 @@ impl GlobalDecorator for FileTreeDecorator {
 struct TreeNode {
     children: BTreeMap<String, TreeNode>,
     is_file: bool,
+    /// Size of the file, or of all files beneath a directory
+    bytes: u64,
 }
 
-fn build_tree(root: &Path, files: &[PathBuf]) -> TreeNode {
+fn build_tree(paths: impl Iterator<Item = (String, u64)>) -> TreeNode {
     let mut root_node = TreeNode::default();
-    for file in files {
-        let relative = file.strip_prefix(root).unwrap_or(file);
+    for (path, bytes) in paths {
         let mut current = &mut root_node;
-        for component in relative.components() {
-            let name = component.as_os_str().to_string_lossy().to_string();
-            current = current.children.entry(name).or_default();
+        for name in path.split('/') {
+            current = current.children.entry(name.to_string()).or_default();
+            current.bytes += bytes;
         }
         current.is_file = true;
     }
     root_node
 }
 
-fn print_tree(node: &TreeNode, prefix: &str, output: &mut String) {
+fn print_tree(node: &TreeNode, prefix: &str, sizes: bool, output: &mut String) {
     let children_count = node.children.len();
     for (i, (name, child)) in node.children.iter().enumerate() {
         let is_last_child = i == children_count - 1;
@@ -68,7 +228,7 @@ fn print_tree(node: &TreeNode, prefix: &str, output: &mut String) {
 
         // For the root's children, we don't need a special prefix at the start,
         // but for nested ones we do.
-        let _ = writeln!(
+        let _ = write!(
             output,
             "{}{}{}{}",
             prefix,
@@ -76,10 +236,230 @@ fn print_tree(node: &TreeNode, prefix: &str, output: &mut String) {
             name,
             if child.is_file { "" } else { "/" }
         );
+        if sizes {
+            let _ = write!(output, " ({})", format_human_size(child.bytes));
+        }
+        output.push('\n');
 
         if !child.children.is_empty() {
             let new_prefix = format!("{}{}", prefix, if is_last_child { "    " } else { "│   " });
-            print_tree(child, &new_prefix, output);
+            print_tree(child, &new_prefix, sizes, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decorator(roots: &[&str], mode: PrologueMode) -> FileTreeDecorator {
+        FileTreeDecorator {
+            roots: roots
+                .iter()
+                .map(|root| TreeRoot {
+                    label: (*root).to_string(),
+                    path: PathBuf::from(root),
+                })
+                .collect(),
+            mode,
+            stats: false,
+            max_file_size: 0,
         }
     }
+
+    #[test]
+    fn test_list_single_root_is_relative() {
+        let files = vec![PathBuf::from("crate-a/src/lib.rs")];
+        let prologue = decorator(&["crate-a"], PrologueMode::List)
+            .prologue(&files)
+            .unwrap();
+
+        assert!(prologue.contains("- src/lib.rs\n"));
+    }
+
+    #[test]
+    fn test_list_multiple_roots_are_prefixed() {
+        let files = vec![
+            PathBuf::from("crate-a/src/lib.rs"),
+            PathBuf::from("crate-b/src/lib.rs"),
+        ];
+        let prologue = decorator(&["crate-a", "crate-b"], PrologueMode::List)
+            .prologue(&files)
+            .unwrap();
+
+        assert!(prologue.contains("- crate-a/src/lib.rs\n"));
+        assert!(prologue.contains("- crate-b/src/lib.rs\n"));
+    }
+
+    #[test]
+    fn test_top_level_dir() {
+        let single = decorator(&["crate-a"], PrologueMode::Off).roots;
+        assert_eq!(
+            top_level_dir(&single, Path::new("crate-a/src/x/lib.rs")),
+            "src/"
+        );
+        assert_eq!(
+            top_level_dir(&single, Path::new("crate-a/Cargo.toml")),
+            "./"
+        );
+
+        let multiple = decorator(&["crate-a", "crate-b"], PrologueMode::Off).roots;
+        assert_eq!(
+            top_level_dir(&multiple, Path::new("crate-b/src/lib.rs")),
+            "crate-b/src/"
+        );
+        assert_eq!(
+            top_level_dir(&multiple, Path::new("crate-a/Cargo.toml")),
+            "crate-a/"
+        );
+    }
+
+    #[test]
+    fn test_dir_at_depth() {
+        let single = decorator(&["crate-a"], PrologueMode::Off).roots;
+        let file = Path::new("crate-a/src/decorator/json/mod.rs");
+        assert_eq!(dir_at_depth(&single, file, 2), "src/decorator/");
+        // Shallower files keep their whole directory
+        assert_eq!(dir_at_depth(&single, file, 5), "src/decorator/json/");
+        assert_eq!(
+            dir_at_depth(&single, Path::new("crate-a/Cargo.toml"), 2),
+            "./"
+        );
+
+        let multiple = decorator(&["crate-a", "crate-b"], PrologueMode::Off).roots;
+        assert_eq!(
+            dir_at_depth(&multiple, Path::new("crate-b/src/x/lib.rs"), 2),
+            "crate-b/src/x/"
+        );
+    }
+
+    #[test]
+    fn test_list_file_root_shows_file_name() {
+        let files = vec![PathBuf::from("src/main.rs")];
+        let prologue = decorator(&["src/main.rs"], PrologueMode::List)
+            .prologue(&files)
+            .unwrap();
+
+        assert!(prologue.contains("- main.rs\n"));
+
+        let files = vec![PathBuf::from("src/main.rs"), PathBuf::from("src/lib.rs")];
+        let prologue = decorator(&["src/main.rs", "src/lib.rs"], PrologueMode::List)
+            .prologue(&files)
+            .unwrap();
+
+        assert!(prologue.contains("- src/main.rs\n- src/lib.rs\n"));
+    }
+
+    #[test]
+    fn test_tree_multiple_roots() {
+        let files = vec![
+            PathBuf::from("crate-a/src/lib.rs"),
+            PathBuf::from("crate-b/main.rs"),
+        ];
+        let prologue = decorator(&["crate-a", "crate-b"], PrologueMode::Tree)
+            .prologue(&files)
+            .unwrap();
+
+        assert_eq!(
+            prologue,
+            "File structure:\n\
+             ├── crate-a/\n\
+             │   └── src/\n\
+             │       └── lib.rs\n\
+             └── crate-b/\n\
+             \x20   └── main.rs\n\n"
+        );
+    }
+
+    #[test]
+    fn test_list_stats() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, "fn main() {\n}\n".repeat(200))?;
+
+        let mut decorator = decorator(&[dir.path().to_str().unwrap()], PrologueMode::List);
+        decorator.stats = true;
+        let prologue = decorator.prologue(std::slice::from_ref(&file)).unwrap();
+
+        assert!(
+            prologue.contains("- main.rs (2.7 KB, 400 lines)\n"),
+            "{prologue}"
+        );
+        assert!(
+            prologue.contains("Total: 1 files, 2.7 KB, 400 lines\n"),
+            "{prologue}"
+        );
+
+        // Files that ingestion skips as too large are not read for a line count
+        decorator.max_file_size = 1024;
+        let prologue = decorator.prologue(&[file]).unwrap();
+        assert!(prologue.contains("- main.rs (2.7 KB)\n"), "{prologue}");
+
+        let partial = dir.path().join("partial.txt");
+        std::fs::write(&partial, "one\ntwo")?;
+        assert_eq!(count_lines(&partial)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_has_tree_and_annotated_list() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("src"))?;
+        let file = dir.path().join("src/main.rs");
+        std::fs::write(&file, "fn main() {}\n")?;
+
+        let decorator = decorator(&[dir.path().to_str().unwrap()], PrologueMode::Full);
+        let prologue = decorator.prologue(&[file]).unwrap();
+
+        assert_eq!(
+            prologue,
+            "File structure:\n\
+             └── src/\n\
+             \x20   └── main.rs\n\n\
+             Files included in this digest:\n\
+             - src/main.rs (13 B, 1 lines)\n\
+             Total: 1 files, 13 B, 1 lines\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_stats() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir_all(dir.path().join("src/decorator"))?;
+        let files = [
+            (dir.path().join("README.md"), 100),
+            (dir.path().join("src/main.rs"), 2000),
+            (dir.path().join("src/decorator/xml.rs"), 3000),
+        ];
+        for (file, len) in &files {
+            std::fs::write(file, "x".repeat(*len))?;
+        }
+
+        let mut decorator = decorator(&[dir.path().to_str().unwrap()], PrologueMode::Tree);
+        decorator.stats = true;
+        let files: Vec<PathBuf> = files.into_iter().map(|(file, _)| file).collect();
+        let prologue = decorator.prologue(&files).unwrap();
+
+        assert_eq!(
+            prologue,
+            "File structure:\n\
+             ├── README.md (100 B)\n\
+             └── src/ (4.9 KB)\n\
+             \x20   ├── decorator/ (2.9 KB)\n\
+             \x20   │   └── xml.rs (2.9 KB)\n\
+             \x20   └── main.rs (2.0 KB)\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_human_size() {
+        assert_eq!(format_human_size(512), "512 B");
+        assert_eq!(format_human_size(4300), "4.2 KB");
+        assert_eq!(format_human_size(5 * 1024 * 1024), "5.0 MB");
+    }
 }
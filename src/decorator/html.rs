@@ -0,0 +1,99 @@
+use super::{ContentDecorator, FileInfo, format_path, header_suffix};
+use std::path::Path;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+details { margin: 0.5em 0; border: 1px solid #ddd; border-radius: 4px; }
+summary { cursor: pointer; padding: 0.4em 0.8em; background: #f6f8fa; font-family: monospace; }
+pre { margin: 0; padding: 0.8em; overflow-x: auto; }
+.skipped { color: #888; font-family: monospace; }";
+
+/// Emits a standalone HTML page with a collapsible section per file
+#[derive(Default)]
+pub struct HtmlDecorator {
+    /// Append the file's token count to its summary
+    pub show_tokens: bool,
+    /// Append the file's line count to its summary
+    pub show_lines: bool,
+}
+
+/// Escapes text for use in HTML element content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+impl ContentDecorator for HtmlDecorator {
+    fn before(&self, path: &Path, info: &FileInfo) -> Option<String> {
+        let path_str = escape_html(&format_path(path));
+        let suffix = header_suffix(self.show_tokens, self.show_lines, info);
+        Some(format!("<details>\n<summary>{path_str}{suffix}</summary>"))
+    }
+
+    fn after(&self, _path: &Path, _info: &FileInfo) -> Option<String> {
+        Some("</details>".to_string())
+    }
+
+    fn transform(&self, _path: &Path, content: String) -> String {
+        format!("<pre><code>{}</code></pre>", escape_html(&content))
+    }
+
+    fn skipped(&self, path: &Path, reason: &str) -> String {
+        format!(
+            "<p class=\"skipped\">{} ({})</p>",
+            escape_html(&format_path(path)),
+            escape_html(reason)
+        )
+    }
+
+    fn header(&self) -> Option<String> {
+        Some(format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>gitmelt digest</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>"
+        ))
+    }
+
+    fn wrap_global(&self, text: String) -> Option<String> {
+        Some(format!("<pre>{}</pre>", escape_html(text.trim_end())))
+    }
+
+    fn footer(&self, _total_tokens: usize) -> Option<String> {
+        Some("</body>\n</html>".to_string())
+    }
+
+    fn extension(&self) -> &str {
+        "html"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_html_decorator_escapes_content() {
+        let decorator = HtmlDecorator::default();
+        let path = PathBuf::from("src/a&b.rs");
+
+        assert_eq!(
+            decorator.before(&path, &FileInfo::default()).unwrap(),
+            "<details>\n<summary>src/a&amp;b.rs</summary>"
+        );
+        assert_eq!(
+            decorator.transform(&path, "if a < b && b > c {}".to_string()),
+            "<pre><code>if a &lt; b &amp;&amp; b &gt; c {}</code></pre>"
+        );
+        assert_eq!(
+            decorator.after(&path, &FileInfo::default()).unwrap(),
+            "</details>"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use super::{ContentDecorator, FileInfo, format_path};
+use std::path::Path;
+
+/// Emits the digest as a JSON document with a `files` array
+pub struct JsonDecorator;
+
+fn quote(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+impl ContentDecorator for JsonDecorator {
+    fn before(&self, path: &Path, info: &FileInfo) -> Option<String> {
+        let tokens = info
+            .tokens
+            .map_or_else(|| "null".to_string(), |t| t.to_string());
+        let lines = info
+            .lines
+            .map_or_else(|| "null".to_string(), |l| l.to_string());
+        let mut fields = format!(
+            "    {{\n      \"path\": {},\n      \"tokens\": {tokens},\n      \"lines\": {lines},",
+            quote(&format_path(path))
+        );
+        if !info.notes.is_empty() {
+            fields.push_str(&format!(
+                "\n      \"notes\": {},",
+                serde_json::Value::from(info.notes.clone())
+            ));
+        }
+        Some(fields)
+    }
+
+    fn after(&self, _path: &Path, _info: &FileInfo) -> Option<String> {
+        Some("    }".to_string())
+    }
+
+    fn transform(&self, _path: &Path, content: String) -> String {
+        format!("      \"content\": {}", quote(&content))
+    }
+
+    fn skipped(&self, path: &Path, reason: &str) -> String {
+        format!(
+            "    {{\n      \"path\": {},\n      \"skipped\": {}\n    }}",
+            quote(&format_path(path)),
+            quote(reason)
+        )
+    }
+
+    fn duplicate(&self, path: &Path, original: &Path) -> String {
+        format!(
+            "    {{\n      \"path\": {},\n      \"duplicate_of\": {}\n    }}",
+            quote(&format_path(path)),
+            quote(&format_path(original))
+        )
+    }
+
+    fn open_dir(&self, name: &str) -> Option<String> {
+        Some(format!("    {{\"dir\": {}, \"files\": [", quote(name)))
+    }
+
+    fn close_dir(&self) -> Option<String> {
+        Some("    ]}".to_string())
+    }
+
+    fn header(&self) -> Option<String> {
+        Some("{\n  \"files\": [".to_string())
+    }
+
+    fn wrap_global(&self, _text: String) -> Option<String> {
+        // Plain-text prologues and epilogues would make the document invalid JSON
+        None
+    }
+
+    fn separator(&self) -> &str {
+        ",\n"
+    }
+
+    fn footer(&self, total_tokens: usize) -> Option<String> {
+        Some(format!("  ],\n  \"total_tokens\": {total_tokens}\n}}"))
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_json_decorator_escapes_content() {
+        let decorator = JsonDecorator;
+        let path = PathBuf::from("src/main.rs");
+        let info = FileInfo {
+            tokens: Some(3),
+            lines: Some(1),
+            notes: vec!["decoded with replacement characters".to_string()],
+        };
+
+        let before = decorator.before(&path, &info).unwrap();
+        let transformed = decorator.transform(&path, "say \"hi\"\n".to_string());
+        let after = decorator.after(&path, &info).unwrap();
+        let entry = format!("{before}\n{transformed}\n{after}");
+
+        let value: serde_json::Value = serde_json::from_str(&entry).unwrap();
+        assert_eq!(value["path"], "src/main.rs");
+        assert_eq!(value["tokens"], 3);
+        assert_eq!(value["lines"], 1);
+        assert_eq!(value["content"], "say \"hi\"\n");
+        assert_eq!(value["notes"][0], "decoded with replacement characters");
+    }
+}
@@ -0,0 +1,68 @@
+use super::ContentDecorator;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Wraps another decorator and prefixes every content line with its line number
+pub struct LineNumberDecorator {
+    pub inner: Box<dyn ContentDecorator>,
+}
+
+/// Numbers each line as `{n} | {line}`, padding numbers to the widest one
+fn number_lines(content: &str) -> String {
+    let line_count = content.lines().count();
+    let width = line_count.to_string().len();
+
+    let mut output = String::with_capacity(content.len() + line_count * (width + 3));
+    for (i, line) in content.lines().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        let _ = write!(output, "{:>width$} | {line}", i + 1);
+    }
+    if content.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+impl ContentDecorator for LineNumberDecorator {
+    fn transform(&self, path: &Path, content: String) -> String {
+        self.inner.transform(path, number_lines(&content))
+    }
+
+    forward_decorator!(inner);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decorator::{DefaultDecorator, FileInfo};
+
+    #[test]
+    fn test_number_lines_pads_to_width() {
+        let content = (1..=10).map(|i| format!("line {i}\n")).collect::<String>();
+        let numbered = number_lines(&content);
+
+        assert!(numbered.starts_with(" 1 | line 1\n 2 | line 2\n"));
+        assert!(numbered.ends_with("10 | line 10\n"));
+    }
+
+    #[test]
+    fn test_line_number_decorator_wraps_inner() {
+        let decorator = LineNumberDecorator {
+            inner: Box::new(DefaultDecorator::default()),
+        };
+        let path = Path::new("main.rs");
+
+        assert_eq!(
+            decorator.transform(path, "fn main() {\n}".to_string()),
+            "1 | fn main() {\n2 | }"
+        );
+        assert!(
+            decorator
+                .before(path, &FileInfo::default())
+                .unwrap()
+                .contains("FILE: main.rs")
+        );
+    }
+}
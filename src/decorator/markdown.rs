@@ -1,22 +1,178 @@
-use super::{ContentDecorator, format_path};
+use super::{ContentDecorator, FileInfo, format_path, header_suffix, shebang_language};
+use std::collections::HashMap;
 use std::path::Path;
 
-pub struct MarkdownDecorator;
+#[derive(Default)]
+pub struct MarkdownDecorator {
+    /// Append the file's token count to its heading
+    pub show_tokens: bool,
+    /// Append the file's line count to its heading
+    pub show_lines: bool,
+    /// Fence language overrides, keyed by extension or by full file name
+    pub lang_map: HashMap<String, String>,
+}
+
+/// Languages for common files without an extension
+const BUILTIN_LANGUAGES: [(&str, &str); 4] = [
+    ("Dockerfile", "dockerfile"),
+    ("Makefile", "makefile"),
+    ("makefile", "makefile"),
+    ("Rakefile", "ruby"),
+];
+
+/// Parses a `--lang-map` value of the form `ext=lang`
+pub fn parse_lang_mapping(input: &str) -> Result<(String, String), String> {
+    match input.split_once('=') {
+        Some((key, lang)) if !key.is_empty() && !lang.is_empty() => {
+            Ok((key.trim_start_matches('.').to_string(), lang.to_string()))
+        }
+        _ => Err(format!("expected EXT=LANG, got '{input}'")),
+    }
+}
+
+/// Backtick fence one longer than the longest backtick run in `content` (at least 3),
+/// so fences inside the file cannot close the code block (CommonMark rule)
+fn fence_for(content: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat((longest_run + 1).max(3))
+}
+
+/// First line of the content, without the gutter added by `--line-numbers`
+fn first_line(content: &str) -> &str {
+    let line = content.lines().next().unwrap_or("");
+    match line.split_once(" | ") {
+        Some((number, rest)) if number.trim().parse::<usize>().is_ok() => rest,
+        _ => line,
+    }
+}
+
+impl MarkdownDecorator {
+    /// Code fence language: user mappings by file name then extension,
+    /// built-in rules and the shebang line for extensionless files, and
+    /// finally the extension itself
+    fn language<'a>(&'a self, path: &'a Path, content: &str) -> &'a str {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+        self.lang_map
+            .get(name)
+            .or_else(|| self.lang_map.get(ext))
+            .map(String::as_str)
+            .or_else(|| {
+                BUILTIN_LANGUAGES
+                    .iter()
+                    .find(|(file, _)| *file == name)
+                    .map(|(_, lang)| *lang)
+            })
+            .or_else(|| {
+                ext.is_empty()
+                    .then(|| first_line(content))
+                    .and_then(shebang_language)
+            })
+            .unwrap_or(ext)
+    }
+}
 
 impl ContentDecorator for MarkdownDecorator {
-    fn before(&self, path: &Path) -> Option<String> {
+    fn before(&self, path: &Path, info: &FileInfo) -> Option<String> {
         let path_str = format_path(path);
-        // Extract extension for syntax highlighting (e.g., "rs", "toml")
-        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let suffix = header_suffix(self.show_tokens, self.show_lines, info);
+
+        Some(format!("## File: {path_str}{suffix}"))
+    }
 
-        Some(format!("## File: {path_str}\n```{ext}"))
+    fn after(&self, _path: &Path, _info: &FileInfo) -> Option<String> {
+        None
     }
 
-    fn after(&self, _path: &Path) -> Option<String> {
-        Some("```".to_string())
+    fn transform(&self, path: &Path, content: String) -> String {
+        // Language for syntax highlighting (e.g., "rs", "toml")
+        let lang = self.language(path, &content);
+        // The fence wraps the content here because its length depends on it
+        let fence = fence_for(&content);
+
+        format!("{fence}{lang}\n{content}\n{fence}")
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_plain_content_uses_three_backticks() {
+        let decorator = MarkdownDecorator::default();
+        let path = Path::new("src/main.rs");
+
+        assert_eq!(
+            decorator.transform(path, "fn main() {}".to_string()),
+            "```rs\nfn main() {}\n```"
+        );
+    }
+
+    #[test]
+    fn test_markdown_fence_longer_than_inner_fence() {
+        let decorator = MarkdownDecorator::default();
+        let path = Path::new("README.md");
+        let content = "# Usage\n```bash\ncargo run\n```\n".to_string();
+
+        assert_eq!(
+            decorator.transform(path, content.clone()),
+            format!("````md\n{content}\n````")
+        );
+    }
+
+    #[test]
+    fn test_markdown_lang_map() {
+        let decorator = MarkdownDecorator {
+            lang_map: HashMap::from([
+                ("tsx".to_string(), "typescript".to_string()),
+                ("Makefile".to_string(), "make".to_string()),
+            ]),
+            ..MarkdownDecorator::default()
+        };
+
+        assert_eq!(
+            decorator.language(Path::new("src/App.tsx"), ""),
+            "typescript"
+        );
+        assert_eq!(decorator.language(Path::new("Makefile"), ""), "make");
+        assert_eq!(
+            decorator.language(Path::new("docker/Dockerfile"), ""),
+            "dockerfile"
+        );
+        assert_eq!(decorator.language(Path::new("main.rs"), ""), "rs");
+        assert_eq!(decorator.language(Path::new("LICENSE"), ""), "");
+    }
+
+    #[test]
+    fn test_markdown_shebang() {
+        let decorator = MarkdownDecorator::default();
+        let script = "#!/usr/bin/env python3\nprint()\n";
+
+        assert_eq!(
+            decorator.language(Path::new("bin/deploy"), script),
+            "python"
+        );
+        assert_eq!(
+            decorator.language(Path::new("bin/deploy"), " 1 | #!/bin/bash\n 2 | ls"),
+            "bash"
+        );
+        // The extension wins when there is one
+        assert_eq!(decorator.language(Path::new("run.sh"), script), "sh");
     }
 
-    fn transform(&self, _path: &Path, content: String) -> String {
-        content
+    #[test]
+    fn test_parse_lang_mapping() {
+        assert_eq!(
+            parse_lang_mapping(".tsx=typescript"),
+            Ok(("tsx".to_string(), "typescript".to_string()))
+        );
+        assert!(parse_lang_mapping("tsx").is_err());
+        assert!(parse_lang_mapping("=ts").is_err());
     }
 }
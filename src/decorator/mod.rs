@@ -1,13 +1,90 @@
+use std::fmt::Write;
 use std::path::Path;
 
+/// Implements every [`ContentDecorator`] method except `transform` by
+/// forwarding it to the decorator in field `$inner`. Wrappers that only
+/// change a file's content invoke it inside their impl, so a method added to
+/// the trait needs forwarding here and nowhere else.
+macro_rules! forward_decorator {
+    ($inner:ident) => {
+        fn before(
+            &self,
+            path: &std::path::Path,
+            info: &$crate::decorator::FileInfo,
+        ) -> Option<String> {
+            self.$inner.before(path, info)
+        }
+
+        fn after(
+            &self,
+            path: &std::path::Path,
+            info: &$crate::decorator::FileInfo,
+        ) -> Option<String> {
+            self.$inner.after(path, info)
+        }
+
+        fn skipped(&self, path: &std::path::Path, reason: &str) -> String {
+            self.$inner.skipped(path, reason)
+        }
+
+        fn duplicate(&self, path: &std::path::Path, original: &std::path::Path) -> String {
+            self.$inner.duplicate(path, original)
+        }
+
+        fn header(&self) -> Option<String> {
+            self.$inner.header()
+        }
+
+        fn wrap_global(&self, text: String) -> Option<String> {
+            self.$inner.wrap_global(text)
+        }
+
+        fn open_dir(&self, name: &str) -> Option<String> {
+            self.$inner.open_dir(name)
+        }
+
+        fn close_dir(&self) -> Option<String> {
+            self.$inner.close_dir()
+        }
+
+        fn separator(&self) -> &str {
+            self.$inner.separator()
+        }
+
+        fn footer(&self, total_tokens: usize) -> Option<String> {
+            self.$inner.footer(total_tokens)
+        }
+
+        fn extension(&self) -> &str {
+            self.$inner.extension()
+        }
+    };
+}
+
+pub mod blank_lines;
 pub mod default;
 pub mod file_tree;
+pub mod html;
+pub mod json;
+pub mod line_numbers;
 pub mod markdown;
+pub mod strip_comments;
+pub mod template;
+pub mod transform_cmd;
+pub mod wrap;
 pub mod xml;
 
+pub use blank_lines::CollapseBlankLinesDecorator;
 pub use default::DefaultDecorator;
-pub use file_tree::FileTreeDecorator;
+pub use file_tree::{FileTreeDecorator, TreeRoot, dir_at_depth, format_human_size, top_level_dir};
+pub use html::HtmlDecorator;
+pub use json::JsonDecorator;
+pub use line_numbers::LineNumberDecorator;
 pub use markdown::MarkdownDecorator;
+pub use strip_comments::StripCommentsDecorator;
+pub use template::{Template, TemplateDecorator, TemplatePrologue};
+pub use transform_cmd::TransformCommandDecorator;
+pub use wrap::WrapDecorator;
 pub use xml::XmlDecorator;
 
 #[derive(clap::ValueEnum, Clone, Debug, Default, PartialEq)]
@@ -15,25 +92,102 @@ pub enum PrologueMode {
     #[default]
     List,
     Tree,
+    /// The tree followed by the list with sizes and line counts
+    Full,
     Off,
 }
 
+/// Details about a file known once its content has been processed
+#[derive(Debug, Default)]
+pub struct FileInfo {
+    /// Token count of the transformed content, `None` when token counting is disabled
+    pub tokens: Option<usize>,
+    /// Line count of the content as read, before any transformation, `None`
+    /// for entries without a body
+    pub lines: Option<usize>,
+    /// Remarks shown alongside the file's header, e.g. how it was decoded
+    pub notes: Vec<String>,
+}
+
 /// Trait for decorating individual file content
 pub trait ContentDecorator: Sync {
     /// Initial text to appear before the file content
-    fn before(&self, path: &Path) -> Option<String>;
+    fn before(&self, path: &Path, info: &FileInfo) -> Option<String>;
 
     /// Text to appear after the file content
-    fn after(&self, path: &Path) -> Option<String>;
+    fn after(&self, path: &Path, info: &FileInfo) -> Option<String>;
 
     /// Transform the content of the file itself
     fn transform(&self, path: &Path, content: String) -> String;
+
+    /// Entry written in place of a file that could not be included
+    fn skipped(&self, path: &std::path::Path, reason: &str) -> String {
+        format!("----- {} ({reason}) -----", path.display())
+    }
+
+    /// Entry written in place of a file whose content is identical to `original`:
+    /// the file's header and footer without a body
+    fn duplicate(&self, path: &std::path::Path, original: &std::path::Path) -> String {
+        let info = FileInfo {
+            tokens: None,
+            lines: None,
+            notes: vec![format!("identical to {}", format_path(original))],
+        };
+        [self.before(path, &info), self.after(path, &info)]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim_end()
+            .to_string()
+    }
+
+    /// Text to appear once at the very beginning of the digest, before the prologue
+    fn header(&self) -> Option<String> {
+        None
+    }
+
+    /// Adapts global prologue or epilogue text to this format, or drops it by returning `None`
+    fn wrap_global(&self, text: String) -> Option<String> {
+        Some(text)
+    }
+
+    /// Opening of a directory element holding the entries below it, for
+    /// nested output. `None` when the format has no nesting.
+    fn open_dir(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// Closing of the directory element most recently opened
+    fn close_dir(&self) -> Option<String> {
+        None
+    }
+
+    /// Text written between two consecutive file entries
+    fn separator(&self) -> &str {
+        "\n"
+    }
+
+    /// Text to appear once at the very end of the digest
+    fn footer(&self, _total_tokens: usize) -> Option<String> {
+        None
+    }
+
+    /// File extension for output written in this format
+    fn extension(&self) -> &str {
+        "txt"
+    }
 }
 
 /// Trait for global decorations on the digest (e.g. at the very start)
 pub trait GlobalDecorator: Sync {
     /// Text to appear at the very beginning of the digest
     fn prologue(&self, files: &[std::path::PathBuf]) -> Option<String>;
+
+    /// Text to appear after the last file of the digest
+    fn epilogue(&self, _files: &[std::path::PathBuf]) -> Option<String> {
+        None
+    }
 }
 
 /// Helper to ensure paths always use forward slashes for the digest
@@ -48,11 +202,109 @@ pub fn format_path(path: &Path) -> String {
         .join("/")
 }
 
+/// Language of a script named by its shebang line, e.g. `python` for
+/// `#!/usr/bin/env python3`. For files whose extension says nothing.
+pub fn shebang_language(first_line: &str) -> Option<&'static str> {
+    let command = first_line.strip_prefix("#!")?;
+    let mut words = command.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    // `env` runs the first argument that is not an option, e.g. `env -S node`
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    // Versioned interpreters such as python3.12 or perl5
+    let interpreter = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    match interpreter {
+        "python" => Some("python"),
+        "bash" => Some("bash"),
+        "sh" | "dash" => Some("sh"),
+        "zsh" => Some("zsh"),
+        "node" | "nodejs" => Some("javascript"),
+        "ruby" => Some("ruby"),
+        "perl" => Some("perl"),
+        "php" => Some("php"),
+        "lua" => Some("lua"),
+        _ => None,
+    }
+}
+
+/// Formats a count with thousands separators (e.g. `1,234`)
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            output.push(',');
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// Suffix such as ` (56 lines) (1,234 tokens) (note)` for headers; lines and
+/// tokens only appear when shown
+fn header_suffix(show_tokens: bool, show_lines: bool, info: &FileInfo) -> String {
+    let mut suffix = String::new();
+    if let Some(lines) = info.lines
+        && show_lines
+    {
+        let _ = write!(suffix, " ({} lines)", format_count(lines));
+    }
+    if let Some(tokens) = info.tokens
+        && show_tokens
+    {
+        let _ = write!(suffix, " ({} tokens)", format_count(tokens));
+    }
+    for note in &info.notes {
+        let _ = write!(suffix, " ({note})");
+    }
+    suffix
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(windows)]
     use std::path::PathBuf;
 
+    #[test]
+    fn test_shebang_language() {
+        assert_eq!(shebang_language("#!/usr/bin/env python3"), Some("python"));
+        assert_eq!(shebang_language("#!/usr/bin/python3.12 -u"), Some("python"));
+        assert_eq!(shebang_language("#!/bin/bash"), Some("bash"));
+        assert_eq!(shebang_language("#! /bin/sh -e"), Some("sh"));
+        assert_eq!(
+            shebang_language("#!/usr/bin/env -S node --no-warnings"),
+            Some("javascript")
+        );
+        assert_eq!(shebang_language("#!/usr/bin/ruby"), Some("ruby"));
+        assert_eq!(shebang_language("#!/usr/bin/perl -w"), Some("perl"));
+        assert_eq!(shebang_language("#!/usr/bin/env"), None);
+        assert_eq!(shebang_language("#!/opt/custom-tool"), None);
+        assert_eq!(shebang_language("# python"), None);
+    }
+
+    #[test]
+    fn test_header_suffix() {
+        let info = FileInfo {
+            tokens: Some(1234),
+            lines: Some(56),
+            notes: vec!["truncated".to_string()],
+        };
+        assert_eq!(header_suffix(false, false, &info), " (truncated)");
+        assert_eq!(
+            header_suffix(true, true, &info),
+            " (56 lines) (1,234 tokens) (truncated)"
+        );
+        // Lines are known without a tokenizer
+        let info = FileInfo {
+            lines: Some(3),
+            ..FileInfo::default()
+        };
+        assert_eq!(header_suffix(true, true, &info), " (3 lines)");
+    }
+
     #[test]
     fn test_format_path_unix() {
         let path = Path::new("src/main.rs");
@@ -71,4 +323,12 @@ mod tests {
         let path = Path::new("./src/main.rs");
         assert_eq!(format_path(path), "src/main.rs");
     }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1234), "1,234");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
 }
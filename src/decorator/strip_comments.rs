@@ -0,0 +1,209 @@
+use super::ContentDecorator;
+use std::path::Path;
+
+/// Wraps another decorator and removes comments from source files before
+/// the inner decorator sees them. Detection is heuristic: quoted strings on a
+/// single line are respected, but multi-line strings and raw strings are not.
+pub struct StripCommentsDecorator {
+    pub inner: Box<dyn ContentDecorator>,
+}
+
+/// Comment markers of a language family
+struct Syntax {
+    line: Option<&'static str>,
+    /// A line comment only starts at the beginning of a line or after whitespace
+    /// (keeps `${#var}` in shell and `a#b` in URLs intact)
+    line_needs_space: bool,
+    block: Option<(&'static str, &'static str)>,
+    /// Characters that open and close string literals
+    quotes: &'static [char],
+}
+
+const C_LIKE: Syntax = Syntax {
+    line: Some("//"),
+    line_needs_space: false,
+    block: Some(("/*", "*/")),
+    quotes: &['"', '`'],
+};
+
+/// CSS has block comments only; `//` appears in URLs
+const CSS: Syntax = Syntax {
+    line: None,
+    line_needs_space: false,
+    block: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+};
+
+const HASH: Syntax = Syntax {
+    line: Some("#"),
+    line_needs_space: true,
+    block: None,
+    quotes: &['"', '\''],
+};
+
+const MARKUP: Syntax = Syntax {
+    line: None,
+    line_needs_space: false,
+    block: Some(("<!--", "-->")),
+    quotes: &[],
+};
+
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let name = path.file_name()?.to_str()?;
+    if matches!(name, "Dockerfile" | "Makefile" | "makefile" | "Rakefile") {
+        return Some(&HASH);
+    }
+
+    match path.extension()?.to_str()? {
+        "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "cs" | "java" | "kt" | "kts"
+        | "scala" | "go" | "swift" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "dart"
+        | "php" | "proto" | "zig" => Some(&C_LIKE),
+        "css" | "scss" | "less" => Some(&CSS),
+        "py" | "sh" | "bash" | "zsh" | "fish" | "toml" | "yaml" | "yml" | "rb" | "pl" | "r"
+        | "ps1" | "cmake" => Some(&HASH),
+        "html" | "htm" | "xml" | "svg" | "xhtml" => Some(&MARKUP),
+        _ => None,
+    }
+}
+
+/// Removes comments, dropping lines that only held a comment and trailing
+/// whitespace left behind on the others. Blank lines that were already blank stay.
+fn strip_comments(content: &str, syntax: &Syntax) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut in_block = false;
+
+    for (line_index, line) in content.split_inclusive('\n').enumerate() {
+        let (body, newline) = match line.strip_suffix('\n') {
+            Some(body) => (body, "\n"),
+            None => (line, ""),
+        };
+
+        // Keep shebangs such as `#!/usr/bin/env python3`
+        if line_index == 0 && body.starts_with("#!") {
+            output.push_str(line);
+            continue;
+        }
+
+        let mut kept = String::with_capacity(body.len());
+        let mut stripped = in_block;
+        let mut quote = None;
+        let mut i = 0;
+        while i < body.len() {
+            let rest = &body[i..];
+
+            if in_block {
+                let end = syntax.block.map_or("", |(_, end)| end);
+                match rest.find(end) {
+                    Some(pos) => {
+                        i += pos + end.len();
+                        in_block = false;
+                    }
+                    None => i = body.len(),
+                }
+                continue;
+            }
+
+            let c = rest.chars().next().unwrap_or_default();
+            if let Some(open) = quote {
+                kept.push(c);
+                i += c.len_utf8();
+                if c == '\\' {
+                    if let Some(escaped) = body[i..].chars().next() {
+                        kept.push(escaped);
+                        i += escaped.len_utf8();
+                    }
+                } else if c == open {
+                    quote = None;
+                }
+                continue;
+            }
+
+            if let Some(marker) = syntax.line
+                && rest.starts_with(marker)
+                && (!syntax.line_needs_space
+                    || kept.is_empty()
+                    || kept.ends_with(char::is_whitespace))
+            {
+                stripped = true;
+                break;
+            }
+            if let Some((start, _)) = syntax.block
+                && rest.starts_with(start)
+            {
+                stripped = true;
+                in_block = true;
+                i += start.len();
+                continue;
+            }
+
+            if syntax.quotes.contains(&c) {
+                quote = Some(c);
+            }
+            kept.push(c);
+            i += c.len_utf8();
+        }
+
+        if stripped {
+            let kept = kept.trim_end();
+            if kept.is_empty() {
+                continue;
+            }
+            output.push_str(kept);
+        } else {
+            output.push_str(&kept);
+        }
+        output.push_str(newline);
+    }
+
+    output
+}
+
+impl ContentDecorator for StripCommentsDecorator {
+    fn transform(&self, path: &Path, content: String) -> String {
+        let content = match syntax_for(path) {
+            Some(syntax) => strip_comments(&content, syntax),
+            None => content,
+        };
+        self.inner.transform(path, content)
+    }
+
+    forward_decorator!(inner);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_c_like() {
+        let content = "// header\nfn main() { // entry\n    /* block\n       comment */\n    let url = \"http://example.com\";\n\n    call(/* inline */ 1);\n}\n";
+
+        assert_eq!(
+            strip_comments(content, &C_LIKE),
+            "fn main() {\n    let url = \"http://example.com\";\n\n    call( 1);\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_hash() {
+        let content = "#!/bin/sh\n# comment\necho \"# not a comment\" # trailing\necho ${#args}\n";
+
+        assert_eq!(
+            strip_comments(content, &HASH),
+            "#!/bin/sh\necho \"# not a comment\"\necho ${#args}\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_markup() {
+        let content = "<root>\n  <!-- note -->\n  <a/><!-- multi\n  line -->\n</root>";
+
+        assert_eq!(strip_comments(content, &MARKUP), "<root>\n  <a/>\n</root>");
+    }
+
+    #[test]
+    fn test_unknown_extension_is_untouched() {
+        assert!(syntax_for(Path::new("notes.txt")).is_none());
+        assert!(syntax_for(Path::new("Dockerfile")).is_some());
+    }
+}
@@ -0,0 +1,245 @@
+use super::{ContentDecorator, FileInfo, GlobalDecorator, format_path, header_suffix};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+const FILES_START: &str = "{{#files}}";
+const FILES_END: &str = "{{/files}}";
+
+/// Placeholders allowed before the `{{#files}}` block
+const HEAD_PLACEHOLDERS: &[&str] = &["prologue"];
+/// Placeholders allowed inside the `{{#files}}` block
+const FILE_PLACEHOLDERS: &[&str] = &["path", "ext", "tokens", "lines", "notes", "content"];
+/// Placeholders allowed after the `{{/files}}` block
+const TAIL_PLACEHOLDERS: &[&str] = &["total_tokens"];
+
+/// A user-supplied digest format, split around its `{{#files}}...{{/files}}` block:
+///
+/// ```text
+/// {{prologue}}
+/// {{#files}}
+/// ## {{path}} ({{tokens}} tokens)
+/// {{content}}
+/// {{/files}}
+/// Total: {{total_tokens}} tokens
+/// ```
+#[derive(Clone, Debug)]
+pub struct Template {
+    head: String,
+    /// Per-file text before and after `{{content}}`
+    file_before: String,
+    file_after: String,
+    tail: String,
+}
+
+/// Drops the line break after a block tag so tags can sit on their own line
+fn skip_tag_newline(text: &str) -> &str {
+    text.strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'))
+        .unwrap_or(text)
+}
+
+/// Names of the `{{name}}` placeholders in `text`
+fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    text.split("{{")
+        .skip(1)
+        .filter_map(|part| part.split_once("}}").map(|(name, _)| name.trim()))
+}
+
+fn check_placeholders(text: &str, allowed: &[&str], section: &str) -> Result<()> {
+    for name in placeholders(text) {
+        if !allowed.contains(&name) {
+            anyhow::bail!(
+                "Unknown placeholder '{{{{{name}}}}}' {section} (expected one of: {})",
+                allowed.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Replaces each `{{name}}` in `text` in a single pass, so substituted values
+/// are never scanned for placeholders themselves
+fn render(text: &str, value: impl Fn(&str) -> String) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.split_once("}}") {
+            Some((name, remaining)) => {
+                output.push_str(&value(name.trim()));
+                rest = remaining;
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+impl Template {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template file {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid template file {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let (head, rest) = text
+            .split_once(FILES_START)
+            .with_context(|| format!("Template must contain a {FILES_START} block"))?;
+        let (file, tail) = rest
+            .split_once(FILES_END)
+            .with_context(|| format!("{FILES_START} block is not closed with {FILES_END}"))?;
+        let file = skip_tag_newline(file);
+        let tail = skip_tag_newline(tail);
+
+        check_placeholders(head, HEAD_PLACEHOLDERS, "before the files block")?;
+        check_placeholders(file, FILE_PLACEHOLDERS, "in the files block")?;
+        check_placeholders(tail, TAIL_PLACEHOLDERS, "after the files block")?;
+
+        // The digest writer already puts line breaks around the content
+        let (file_before, file_after) = file
+            .split_once("{{content}}")
+            .context("The files block must contain {{content}}")?;
+        let file_before = file_before.strip_suffix('\n').unwrap_or(file_before);
+        let file_after = skip_tag_newline(file_after);
+        let file_after = file_after.strip_suffix('\n').unwrap_or(file_after);
+
+        Ok(Self {
+            head: head.to_string(),
+            file_before: file_before.to_string(),
+            file_after: file_after.to_string(),
+            tail: tail.trim_end().to_string(),
+        })
+    }
+
+    fn render_file(&self, text: &str, path: &Path, info: &FileInfo) -> Option<String> {
+        if text.is_empty() {
+            return None;
+        }
+        Some(render(text, |name| match name {
+            "path" => format_path(path),
+            "ext" => path
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            "tokens" => info.tokens.map(|t| t.to_string()).unwrap_or_default(),
+            "lines" => info.lines.map(|l| l.to_string()).unwrap_or_default(),
+            "notes" => header_suffix(false, false, info).trim_start().to_string(),
+            _ => String::new(),
+        }))
+    }
+}
+
+/// Renders each file with the files block of a [`Template`]
+pub struct TemplateDecorator {
+    pub template: Template,
+}
+
+impl ContentDecorator for TemplateDecorator {
+    fn before(&self, path: &Path, info: &FileInfo) -> Option<String> {
+        self.template
+            .render_file(&self.template.file_before, path, info)
+    }
+
+    fn after(&self, path: &Path, info: &FileInfo) -> Option<String> {
+        self.template
+            .render_file(&self.template.file_after, path, info)
+    }
+
+    fn transform(&self, _path: &Path, content: String) -> String {
+        content
+    }
+
+    fn footer(&self, total_tokens: usize) -> Option<String> {
+        if self.template.tail.is_empty() {
+            return None;
+        }
+        Some(render(&self.template.tail, |_| total_tokens.to_string()))
+    }
+}
+
+/// Renders the part of a [`Template`] before the files block, placing the
+/// inner decorator's prologue at `{{prologue}}`
+pub struct TemplatePrologue<'a> {
+    pub template: Template,
+    pub inner: &'a dyn GlobalDecorator,
+}
+
+impl GlobalDecorator for TemplatePrologue<'_> {
+    fn prologue(&self, files: &[PathBuf]) -> Option<String> {
+        let prologue = self.inner.prologue(files).unwrap_or_default();
+        let head = render(&self.template.head, |_| prologue.trim_end().to_string());
+        (!head.trim().is_empty()).then_some(head)
+    }
+
+    fn epilogue(&self, files: &[PathBuf]) -> Option<String> {
+        self.inner.epilogue(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "# Digest\n{{prologue}}\n{{#files}}\n## {{path}} ({{tokens}} tokens)\n```{{ext}}\n{{content}}\n```\n{{/files}}\nTotal: {{total_tokens}}\n";
+
+    #[test]
+    fn test_template_file_block() -> Result<()> {
+        let decorator = TemplateDecorator {
+            template: Template::parse(TEMPLATE)?,
+        };
+        let path = Path::new("src/main.rs");
+        let info = FileInfo {
+            tokens: Some(7),
+            lines: None,
+            notes: Vec::new(),
+        };
+
+        assert_eq!(
+            decorator.before(path, &info).as_deref(),
+            Some("## src/main.rs (7 tokens)\n```rs")
+        );
+        assert_eq!(decorator.after(path, &info).as_deref(), Some("```"));
+        assert_eq!(decorator.footer(42).as_deref(), Some("Total: 42"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_template_prologue() -> Result<()> {
+        struct Fixed;
+        impl GlobalDecorator for Fixed {
+            fn prologue(&self, _files: &[PathBuf]) -> Option<String> {
+                Some("- {{path}}\n".to_string())
+            }
+        }
+
+        let prologue = TemplatePrologue {
+            template: Template::parse(TEMPLATE)?,
+            inner: &Fixed,
+        };
+
+        // Substituted text is not rendered again
+        assert_eq!(
+            prologue.prologue(&[]).as_deref(),
+            Some("# Digest\n- {{path}}\n")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(Template::parse("{{content}}").is_err());
+        assert!(Template::parse("{{#files}}{{content}}").is_err());
+        assert!(Template::parse("{{#files}}{{path}}{{/files}}").is_err());
+
+        let error = Template::parse("{{#files}}{{content}} {{size}}{{/files}}").unwrap_err();
+        assert!(error.to_string().contains("'{{size}}'"), "{error}");
+    }
+}
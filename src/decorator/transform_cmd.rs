@@ -0,0 +1,110 @@
+use super::{ContentDecorator, format_path};
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Wraps another decorator and pipes each file's content through a shell
+/// command before the inner decorator sees it. The command gets the content
+/// on stdin and the file's path, as shown in its header, in `GITMELT_PATH`;
+/// its stdout replaces the content. A command that cannot be started, exits
+/// with a nonzero status or prints invalid UTF-8 leaves the content
+/// unchanged, with a warning.
+///
+/// One process is spawned per file. Files are transformed on the ingest
+/// worker threads, so `--threads` bounds how many run at once.
+pub struct TransformCommandDecorator {
+    pub inner: Box<dyn ContentDecorator>,
+    pub command: String,
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// Runs `command` with `content` on stdin and returns its stdout
+fn run_transform(command: &str, path: &Path, content: &str) -> Result<String> {
+    let mut child = shell(command)
+        .env("GITMELT_PATH", format_path(path))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .context("failed to start")?;
+
+    // Written from another thread so a command that prints before reading
+    // everything cannot fill the stdout pipe and deadlock
+    let mut stdin = child.stdin.take().context("stdin was not captured")?;
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || {
+            // A command that ignores its input closes the pipe early; only
+            // its exit status matters
+            let _ = stdin.write_all(content.as_bytes());
+        });
+        child.wait_with_output()
+    })?;
+
+    if !output.status.success() {
+        bail!("{}", output.status);
+    }
+    String::from_utf8(output.stdout).context("output is not valid UTF-8")
+}
+
+impl ContentDecorator for TransformCommandDecorator {
+    fn transform(&self, path: &Path, content: String) -> String {
+        let content = match run_transform(&self.command, path, &content) {
+            Ok(transformed) => transformed,
+            Err(err) => {
+                notice!(
+                    "--transform-cmd failed for {} ({err:#}), keeping the original content",
+                    format_path(path)
+                );
+                content
+            }
+        };
+        self.inner.transform(path, content)
+    }
+
+    forward_decorator!(inner);
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_replaces_content() {
+        let path = Path::new("./src/main.rs");
+        assert_eq!(
+            run_transform("tr a-z A-Z", path, "fn main() {}\n").unwrap(),
+            "FN MAIN() {}\n"
+        );
+        assert_eq!(
+            run_transform("printf %s \"$GITMELT_PATH\"", path, "").unwrap(),
+            "src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_failure_keeps_original() {
+        let path = Path::new("a.txt");
+        assert!(run_transform("cat; exit 3", path, "text").is_err());
+        // A command that never reads its input must not hang the write
+        let large = "x".repeat(1 << 20);
+        assert!(run_transform("exit 1", path, &large).is_err());
+
+        let decorator = TransformCommandDecorator {
+            inner: Box::new(crate::decorator::DefaultDecorator::default()),
+            command: "echo partial; exit 1".to_string(),
+        };
+        assert_eq!(decorator.transform(path, "text".to_string()), "text");
+    }
+}
@@ -0,0 +1,114 @@
+use super::ContentDecorator;
+use std::path::Path;
+
+/// Marks the lines a wrapped line continues on
+const CONTINUATION: &str = "↪ ";
+
+/// Wraps another decorator, breaking lines longer than `width` characters
+/// before the inner decorator sees the content
+pub struct WrapDecorator {
+    pub inner: Box<dyn ContentDecorator>,
+    pub width: usize,
+    /// Content carries `{n} | ` line number gutters; continuation lines get a
+    /// blank gutter instead of a number of their own
+    pub numbered: bool,
+}
+
+fn wrap_lines(content: &str, width: usize, numbered: bool) -> String {
+    let mut output = String::with_capacity(content.len());
+    for (i, line) in content.lines().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        let (gutter, text) = match line.find(" | ") {
+            Some(end) if numbered => line.split_at(end + 3),
+            _ => ("", line),
+        };
+        wrap_line(&mut output, gutter, text, width);
+    }
+    if content.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+/// Appends `gutter` and `text`, continuing on new lines whenever a line would
+/// exceed `width` characters
+fn wrap_line(output: &mut String, gutter: &str, text: &str, width: usize) {
+    let blank_gutter: String = gutter
+        .chars()
+        .map(|c| if c == '|' { c } else { ' ' })
+        .collect();
+    let mut prefix = gutter.to_string();
+    let mut rest = text;
+    loop {
+        let available = width.saturating_sub(prefix.chars().count()).max(1);
+        if rest.chars().count() <= available {
+            output.push_str(&prefix);
+            output.push_str(rest);
+            return;
+        }
+        let (segment, remainder) = split_at_width(rest, available);
+        output.push_str(&prefix);
+        output.push_str(segment);
+        output.push('\n');
+        rest = remainder;
+        prefix = format!("{blank_gutter}{CONTINUATION}");
+    }
+}
+
+/// Splits off at most `width` characters, at the last whitespace that fits or
+/// mid-word when there is none
+fn split_at_width(text: &str, width: usize) -> (&str, &str) {
+    let end = text
+        .char_indices()
+        .nth(width)
+        .map_or(text.len(), |(i, _)| i);
+    // Whitespace right after the limit is a clean break too
+    let window_end = text[end..]
+        .chars()
+        .next()
+        .map_or(end, |c| end + c.len_utf8());
+    let word_break = text[..window_end]
+        .rfind(char::is_whitespace)
+        .filter(|&i| !text[..i].trim().is_empty());
+    match word_break {
+        Some(i) => (text[..i].trim_end(), text[i..].trim_start()),
+        None => text.split_at(end),
+    }
+}
+
+impl ContentDecorator for WrapDecorator {
+    fn transform(&self, path: &Path, content: String) -> String {
+        self.inner
+            .transform(path, wrap_lines(&content, self.width, self.numbered))
+    }
+
+    forward_decorator!(inner);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_at_word_boundaries() {
+        assert_eq!(
+            wrap_lines("short\nthe quick brown fox jumps\n", 12, false),
+            "short\nthe quick\n↪ brown fox\n↪ jumps\n"
+        );
+    }
+
+    #[test]
+    fn test_wrap_unbreakable() {
+        assert_eq!(wrap_lines("abcdefghij", 4, false), "abcd\n↪ ef\n↪ gh\n↪ ij");
+    }
+
+    #[test]
+    fn test_wrap_numbered() {
+        assert_eq!(
+            wrap_lines(" 9 | let a = 1;\n10 | call(alpha, beta, gamma);", 20, true),
+            " 9 | let a = 1;\n10 | call(alpha,\n   | ↪ beta, gamma);"
+        );
+    }
+}
@@ -1,20 +1,86 @@
-use super::{ContentDecorator, format_path};
+use super::{ContentDecorator, FileInfo, format_path};
+use std::fmt::Write;
 use std::path::Path;
 
-pub struct XmlDecorator;
+#[derive(Default)]
+pub struct XmlDecorator {
+    /// Add a `tokens` attribute to each `<file>` element
+    pub show_tokens: bool,
+    /// Add a `lines` attribute to each `<file>` element
+    pub show_lines: bool,
+}
+
+/// Escapes text for use inside a double-quoted XML attribute
+fn escape_attribute(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+/// Wraps content in a CDATA section, splitting any `]]>` across two sections
+fn cdata(content: &str) -> String {
+    format!("<![CDATA[{}]]>", content.replace("]]>", "]]]]><![CDATA[>"))
+}
 
 impl ContentDecorator for XmlDecorator {
-    fn before(&self, path: &Path) -> Option<String> {
-        let path_str = format_path(path);
-        Some(format!("<file path=\"{path_str}\">"))
+    fn before(&self, path: &Path, info: &FileInfo) -> Option<String> {
+        let path_str = escape_attribute(&format_path(path));
+        let mut attributes = format!("path=\"{path_str}\"");
+        if let Some(lines) = info.lines
+            && self.show_lines
+        {
+            let _ = write!(attributes, " lines=\"{lines}\"");
+        }
+        if let Some(tokens) = info.tokens
+            && self.show_tokens
+        {
+            let _ = write!(attributes, " tokens=\"{tokens}\"");
+        }
+        if !info.notes.is_empty() {
+            let _ = write!(
+                attributes,
+                " note=\"{}\"",
+                escape_attribute(&info.notes.join("; "))
+            );
+        }
+        Some(format!("<file {attributes}>"))
     }
 
-    fn after(&self, _path: &Path) -> Option<String> {
+    fn after(&self, _path: &Path, _info: &FileInfo) -> Option<String> {
         Some("</file>".to_string())
     }
 
     fn transform(&self, _path: &Path, content: String) -> String {
-        content
+        cdata(&content)
+    }
+
+    fn skipped(&self, path: &Path, reason: &str) -> String {
+        format!(
+            "<file path=\"{}\" skipped=\"{}\"/>",
+            escape_attribute(&format_path(path)),
+            escape_attribute(reason)
+        )
+    }
+
+    fn open_dir(&self, name: &str) -> Option<String> {
+        Some(format!("<dir name=\"{}\">", escape_attribute(name)))
+    }
+
+    fn close_dir(&self) -> Option<String> {
+        Some("</dir>".to_string())
+    }
+
+    fn extension(&self) -> &str {
+        "xml"
     }
 }
 
@@ -25,16 +91,77 @@ mod tests {
 
     #[test]
     fn test_xml_decorator() {
-        let decorator = XmlDecorator;
+        let decorator = XmlDecorator::default();
         let path = PathBuf::from("src/main.rs");
         let content = "println!(\"hello\");".to_string();
 
-        let before = decorator.before(&path).unwrap();
-        let after = decorator.after(&path).unwrap();
+        let before = decorator.before(&path, &FileInfo::default()).unwrap();
+        let after = decorator.after(&path, &FileInfo::default()).unwrap();
         let transformed = decorator.transform(&path, content.clone());
 
         assert_eq!(before, "<file path=\"src/main.rs\">");
         assert_eq!(after, "</file>");
-        assert_eq!(transformed, content);
+        assert_eq!(transformed, format!("<![CDATA[{content}]]>"));
+    }
+
+    #[test]
+    fn test_xml_dir_elements() {
+        let decorator = XmlDecorator::default();
+        assert_eq!(decorator.open_dir("a&b").unwrap(), "<dir name=\"a&amp;b\">");
+        assert_eq!(decorator.close_dir().unwrap(), "</dir>");
+    }
+
+    #[test]
+    fn test_xml_decorator_show_tokens() {
+        let decorator = XmlDecorator {
+            show_tokens: true,
+            ..XmlDecorator::default()
+        };
+        let path = PathBuf::from("src/main.rs");
+        let info = FileInfo {
+            tokens: Some(1234),
+            lines: Some(56),
+            ..FileInfo::default()
+        };
+
+        assert_eq!(
+            decorator.before(&path, &info).unwrap(),
+            "<file path=\"src/main.rs\" tokens=\"1234\">"
+        );
+
+        let decorator = XmlDecorator {
+            show_tokens: true,
+            show_lines: true,
+        };
+        assert_eq!(
+            decorator.before(&path, &info).unwrap(),
+            "<file path=\"src/main.rs\" lines=\"56\" tokens=\"1234\">"
+        );
+    }
+
+    #[test]
+    fn test_xml_decorator_escapes_angle_brackets() {
+        let decorator = XmlDecorator::default();
+        let path = PathBuf::from("src/a&\"b\".rs");
+
+        assert_eq!(
+            decorator.before(&path, &FileInfo::default()).unwrap(),
+            "<file path=\"src/a&amp;&quot;b&quot;.rs\">"
+        );
+        assert_eq!(
+            decorator.transform(&path, "if a < b && c > d {}".to_string()),
+            "<![CDATA[if a < b && c > d {}]]>"
+        );
+    }
+
+    #[test]
+    fn test_xml_decorator_splits_cdata_end() {
+        let decorator = XmlDecorator::default();
+        let path = PathBuf::from("data.xml");
+
+        assert_eq!(
+            decorator.transform(&path, "<x><![CDATA[inner]]></x>".to_string()),
+            "<![CDATA[<x><![CDATA[inner]]]]><![CDATA[></x>]]>"
+        );
     }
 }
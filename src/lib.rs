@@ -0,0 +1,290 @@
+//! Turns directories and git repositories into a single text digest.
+//!
+//! [`run`] does what the `gitmelt` binary does: resolve the inputs (cloning
+//! git URLs, extracting archives), traverse them and ingest the files into one digest.
+//!
+//! ```no_run
+//! use gitmelt::ingest::OutputDestination;
+//!
+//! let metrics = gitmelt::run(gitmelt::Options {
+//!     inputs: vec!["src".to_string()],
+//!     output: OutputDestination::Buffer,
+//!     ..gitmelt::Options::default()
+//! })?;
+//! println!("{}", metrics.output.unwrap_or_default());
+//! # anyhow::Ok(())
+//! ```
+//!
+//! The modules expose the individual stages for finer control.
+
+/// Log target of the warnings the `gitmelt` binary shows without `--verbose`
+pub const NOTICE_TARGET: &str = "gitmelt::notice";
+
+/// Logs a warning under [`NOTICE_TARGET`]
+macro_rules! notice {
+    ($($arg:tt)+) => {
+        log::warn!(target: $crate::NOTICE_TARGET, $($arg)+)
+    };
+}
+
+pub mod archive;
+pub mod binary;
+pub mod cloner;
+pub mod decorator;
+pub mod ingest;
+pub mod redact;
+pub mod traversal;
+
+use anyhow::Result;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+
+use crate::cloner::{Checkout, CloneOptions};
+use crate::decorator::{
+    ContentDecorator, DefaultDecorator, FileTreeDecorator, PrologueMode, TreeRoot,
+};
+use crate::ingest::{IngestMetrics, IngestOptions, OutputDestination};
+use crate::traversal::TraversalOptions;
+
+/// Everything [`run`] needs to produce a digest
+pub struct Options {
+    /// Paths to traverse or Git URLs, concatenated in the given order
+    pub inputs: Vec<String>,
+    /// How git URLs are cloned
+    pub clone: CloneOptions,
+    /// Applied to every input; `root` is replaced by each input's path
+    pub traversal: TraversalOptions,
+    pub ingest: IngestOptions,
+    pub output: OutputDestination,
+    pub content_decorator: Box<dyn ContentDecorator>,
+    pub prologue: PrologueMode,
+    /// Show each listed file's size and line count in the prologue
+    pub prologue_stats: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            inputs: vec![".".to_string()],
+            clone: CloneOptions::default(),
+            // Skip empty files like the binary does
+            traversal: TraversalOptions {
+                skip_empty: true,
+                ..TraversalOptions::default()
+            },
+            ingest: IngestOptions::default(),
+            output: OutputDestination::Buffer,
+            content_decorator: Box::new(DefaultDecorator::default()),
+            prologue: PrologueMode::default(),
+            prologue_stats: false,
+        }
+    }
+}
+
+/// Inputs resolved to local directories. Clones of git URLs and extracted
+/// archives live as long as this value; temporary ones are deleted when it
+/// is dropped.
+pub struct Inputs {
+    pub roots: Vec<TreeRoot>,
+    checkouts: Vec<Checkout>,
+}
+
+/// Clones the git URLs and extracts the archives among `inputs`, and returns
+/// a root for every input
+pub fn resolve_inputs(inputs: &[String], clone_options: &CloneOptions) -> Result<Inputs> {
+    let mut checkouts = Vec::new();
+    let mut roots = Vec::new();
+
+    for input in inputs {
+        let (label, root_path) = if cloner::is_git_url(input) {
+            let checkout = cloner::clone_repo(input, clone_options)?;
+            let root_path = match &clone_options.subdir {
+                Some(subdir) => checkout.path().join(subdir),
+                None => checkout.path().to_path_buf(),
+            };
+            checkouts.push(checkout);
+            (cloner::repo_name(input), root_path)
+        } else if archive::is_archive(input) {
+            let temp = archive::extract(Path::new(input), archive::MAX_EXTRACTED_SIZE)?;
+            let root_path = archive::content_root(temp.path());
+            checkouts.push(Checkout::Temp(temp));
+            (archive::archive_name(input), root_path)
+        } else {
+            if clone_options.subdir.is_some() {
+                warn!("--subdir is ignored for local path {input}");
+            }
+            (input.clone(), PathBuf::from(input))
+        };
+
+        roots.push(TreeRoot {
+            label,
+            path: root_path,
+        });
+    }
+
+    Ok(Inputs {
+        roots: distinct_roots(roots),
+        checkouts,
+    })
+}
+
+/// Drops roots inside an earlier or enclosing root, whose files would
+/// otherwise be listed twice, and makes repeated labels unique with a `-2`,
+/// `-3`... suffix so files from different roots keep distinct names
+fn distinct_roots(roots: Vec<TreeRoot>) -> Vec<TreeRoot> {
+    let canonical = |root: &TreeRoot| root.path.canonicalize().unwrap_or(root.path.clone());
+    let paths: Vec<PathBuf> = roots.iter().map(canonical).collect();
+
+    let mut kept: Vec<TreeRoot> = Vec::new();
+    for (i, root) in roots.into_iter().enumerate() {
+        let enclosing = paths.iter().enumerate().find(|&(j, path)| {
+            j != i && paths[i].starts_with(path) && (paths[i] != *path || j < i)
+        });
+        if let Some((j, _)) = enclosing {
+            warn!(
+                "Skipping input {}: it is inside input {}",
+                root.label,
+                paths[j].display()
+            );
+            continue;
+        }
+
+        let mut label = root.label.clone();
+        let mut n = 1;
+        while kept.iter().any(|other| other.label == label) {
+            n += 1;
+            label = format!("{}-{n}", root.label);
+        }
+        kept.push(TreeRoot { label, ..root });
+    }
+    kept
+}
+
+/// Short name of an input for output file names: the repository name of a
+/// git URL, the archive name without extension, or the directory name
+pub fn input_name(input: &str) -> String {
+    if cloner::is_git_url(input) {
+        return cloner::repo_name(input);
+    }
+    if archive::is_archive(input) {
+        return archive::archive_name(input);
+    }
+    // `.` and `..` only get a name once resolved
+    let path = Path::new(input);
+    path.canonicalize()
+        .ok()
+        .as_deref()
+        .unwrap_or(path)
+        .file_name()
+        .map_or_else(
+            || input.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+}
+
+/// Traverses every root with the shared options and concatenates the results
+pub fn collect_files(base: &TraversalOptions, roots: &[TreeRoot]) -> Result<Vec<PathBuf>> {
+    collect_files_with(base, roots, &|_| {})
+}
+
+/// Like [`collect_files`], calling `on_file` for each file as the walk finds it
+pub fn collect_files_with(
+    base: &TraversalOptions,
+    roots: &[TreeRoot],
+    on_file: &(dyn Fn(&Path) + Sync),
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for root in roots {
+        let options = TraversalOptions {
+            root: root.path.clone(),
+            ..base.clone()
+        };
+        info!("Traversing files in {}", options.root.display());
+        files.extend(traversal::traverse_with(&options, on_file)?);
+    }
+    Ok(files)
+}
+
+/// Resolves the inputs, collects their files and writes the digest. Nothing
+/// is written when no files match.
+pub fn run(options: Options) -> Result<IngestMetrics> {
+    let inputs = resolve_inputs(&options.inputs, &options.clone)?;
+
+    let global_decorator = FileTreeDecorator {
+        roots: inputs.roots.clone(),
+        mode: options.prologue,
+        stats: options.prologue_stats,
+        max_file_size: options.ingest.max_file_size,
+    };
+    // Files are processed as the walk finds them
+    let (_, metrics) = ingest::ingest_streaming(
+        |on_file| collect_files_with(&options.traversal, &inputs.roots, on_file),
+        options.output,
+        options.content_decorator.as_ref(),
+        Some(&global_decorator),
+        &options.ingest,
+    )?;
+    // Clones must outlive ingestion
+    drop(inputs.checkouts);
+
+    match metrics {
+        Some(metrics) => Ok(metrics),
+        None => {
+            info!("No files found matching patterns.");
+            Ok(IngestMetrics::default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_run_to_buffer() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}")?;
+        std::fs::write(dir.path().join("notes.txt"), "notes")?;
+
+        let metrics = run(Options {
+            inputs: vec![dir.path().to_string_lossy().into_owned()],
+            traversal: TraversalOptions {
+                include: vec!["*.rs".to_string()],
+                ..TraversalOptions::default()
+            },
+            ..Options::default()
+        })?;
+
+        assert_eq!(metrics.file_count, 1);
+        let output = metrics.output.unwrap();
+        assert!(output.contains("fn main() {}"));
+        assert!(!output.contains("notes"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_roots() -> Result<()> {
+        let dir = tempdir()?;
+        let root = |label: &str, path: &str| TreeRoot {
+            label: label.to_string(),
+            path: dir.path().join(path),
+        };
+        for path in ["a/sub", "b/util", "c/util"] {
+            std::fs::create_dir_all(dir.path().join(path))?;
+        }
+
+        let roots = distinct_roots(vec![
+            root("a/sub", "a/sub"),
+            root("a", "a"),
+            root("a/", "a/"),
+            root("util", "b/util"),
+            root("util", "c/util"),
+        ]);
+        let labels: Vec<&str> = roots.iter().map(|root| root.label.as_str()).collect();
+        assert_eq!(labels, ["a", "util", "util-2"]);
+
+        Ok(())
+    }
+}
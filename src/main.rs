@@ -1,26 +1,39 @@
-mod cloner;
-mod decorator;
-mod ingest;
-mod traversal;
-
-use anyhow::Result;
-use clap::Parser;
-use log::{LevelFilter, info};
+mod config;
+mod picker;
+mod watch;
+
+use anyhow::{Context, Result};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use log::{LevelFilter, info, warn};
+use regex::Regex;
 use std::env;
-use std::path::PathBuf;
-use std::time::Instant;
-use traversal::TraversalOptions;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use crate::decorator::{
-    ContentDecorator, DefaultDecorator, FileTreeDecorator, MarkdownDecorator, XmlDecorator,
+use crate::config::Config;
+use gitmelt::archive;
+use gitmelt::binary::BinaryMode;
+use gitmelt::cloner::{self, CloneOptions};
+use gitmelt::decorator::{
+    CollapseBlankLinesDecorator, ContentDecorator, DefaultDecorator, FileTreeDecorator,
+    GlobalDecorator, HtmlDecorator, JsonDecorator, LineNumberDecorator, MarkdownDecorator,
+    PrologueMode, StripCommentsDecorator, Template, TemplateDecorator, TemplatePrologue,
+    TransformCommandDecorator, TreeRoot, WrapDecorator, XmlDecorator, format_count,
 };
-use crate::ingest::OutputDestination;
+use gitmelt::ingest::{self, IngestMetrics, IngestOptions, OutputDestination, TokenModel};
+use gitmelt::redact::RedactPreset;
+use gitmelt::traversal::{self, SortOrder, TraversalOptions};
 
 #[derive(clap::ValueEnum, Clone, Debug)]
 enum Preset {
     Default,
     Markdown,
     Xml,
+    Json,
+    Html,
 }
 
 #[derive(Parser)]
@@ -28,14 +41,55 @@ enum Preset {
 #[command(about = "Concatenates file contents into a single digest file", long_about = None)]
 #[allow(clippy::struct_excessive_bools)]
 struct Cli {
-    /// Path to traverse or Git URL
+    /// Paths to traverse or Git URLs, concatenated in the given order
     #[arg(default_value = ".")]
-    input: String,
+    input: Vec<String>,
 
     /// Git branch to clone (if input is a git URL)
     #[arg(long)]
     branch: Option<String>,
 
+    /// Commit SHA or tag to check out (if input is a git URL)
+    #[arg(long, conflicts_with = "branch")]
+    rev: Option<String>,
+
+    /// Access token for cloning private repositories over HTTPS
+    #[arg(long, env = "GITMELT_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// Also clone the repository's submodules (if input is a git URL)
+    #[arg(long)]
+    submodules: bool,
+
+    /// Clone the full history instead of only the latest commit, e.g. for --rev or
+    /// --only-changed with older refs
+    #[arg(long, visible_alias = "no-clone-depth")]
+    full_clone: bool,
+
+    /// Directory for cached clones (default: the OS cache directory)
+    #[arg(long, value_name = "PATH")]
+    cache_dir: Option<PathBuf>,
+
+    /// Always clone into a fresh temporary directory
+    #[arg(long, conflicts_with = "cache_dir")]
+    no_cache: bool,
+
+    /// Abort each git clone or fetch after this many seconds (default: no timeout)
+    #[arg(long, value_name = "SECONDS")]
+    clone_timeout: Option<u64>,
+
+    /// Retry a failed git clone up to N times, waiting 1s, 2s, 4s... in between
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    clone_retries: u32,
+
+    /// SSH private key for cloning git@ URLs (default: ssh agent and default keys)
+    #[arg(long, value_name = "PATH")]
+    ssh_key: Option<PathBuf>,
+
+    /// Only check out this subdirectory of the repository (if input is a git URL)
+    #[arg(long)]
+    subdir: Option<String>,
+
     /// Include patterns (glob)
     #[arg(short, long)]
     include: Vec<String>,
@@ -44,37 +98,477 @@ struct Cli {
     #[arg(short, long)]
     exclude: Vec<String>,
 
-    /// Output file path (default: digest.txt in current directory)
+    /// Skip directories named DIR (or the path DIR from the input) without walking into them (repeatable)
+    #[arg(long, value_name = "DIR")]
+    exclude_dir: Vec<String>,
+
+    /// Only include files with these extensions, e.g. `rs,toml` (case-insensitive)
+    #[arg(long, value_name = "EXTS", value_delimiter = ',', value_parser = traversal::parse_extension)]
+    include_ext: Vec<String>,
+
+    /// Exclude files with these extensions, e.g. `lock,png` (case-insensitive)
+    #[arg(long, value_name = "EXTS", value_delimiter = ',', value_parser = traversal::parse_extension)]
+    exclude_ext: Vec<String>,
+
+    /// Read patterns from a file: one `+include` or `-exclude` glob per line, appended after -i/-e
+    #[arg(long, value_name = "PATH")]
+    patterns_file: Option<PathBuf>,
+
+    /// Digest exactly the files listed in PATH ("-" for stdin), one per line, instead of traversing
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["input", "watch"])]
+    files_from: Option<PathBuf>,
+
+    /// Follow symbolic links during traversal
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Maximum directory depth to recurse into (1 = only files in the root)
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Order of files in the digest (path, size: largest first, mtime: newest first)
+    #[arg(long, value_enum, default_value_t = SortOrder::Path)]
+    sort: SortOrder,
+
+    /// Reverse the sort order
+    #[arg(long)]
+    reverse: bool,
+
+    /// Order files by the globs listed in FILE, one per line: files matching earlier globs come first,
+    /// unmatched files last
+    #[arg(long, value_name = "FILE")]
+    concat_order: Option<PathBuf>,
+
+    /// Do not respect .gitignore files (nor .git/info/exclude and the global git ignore)
+    #[arg(long)]
+    no_gitignore: bool,
+
+    /// Apply .gitignore files even in directories that are not git repositories
+    #[arg(long, conflicts_with_all = ["no_gitignore", "no_ignore"])]
+    apply_gitignore_always: bool,
+
+    /// Respect generic .ignore files
+    #[arg(long, conflicts_with = "no_ignore")]
+    ignore_files: bool,
+
+    /// Do not respect any ignore files, including .gitignore and .gitmeltignore
+    #[arg(long)]
+    no_ignore: bool,
+
+    /// Include hidden files and directories (dotfiles); ignore files still apply
+    #[arg(long)]
+    hidden: bool,
+
+    /// Match --include and --exclude patterns case-insensitively
+    #[arg(long)]
+    glob_case_insensitive: bool,
+
+    /// Fail on unreadable directories or files instead of skipping them
+    #[arg(long)]
+    strict: bool,
+
+    /// Stop collecting files after N per input (which N depends on walk order; --sort only orders them)
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Output file path, or - for stdout (default: digest.txt in current directory)
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Output file name used when --output is not given. {repo} is replaced by
+    /// the input's repository or directory name, {date} by today's date
+    #[arg(long, value_name = "TEMPLATE", default_value = ingest::DIGEST_FILENAME, conflicts_with = "output")]
+    output_name: String,
+
     /// Print output to stdout instead of file
     #[arg(long, conflicts_with = "output")]
     stdout: bool,
 
+    /// Copy the digest to the system clipboard instead of writing a file. On
+    /// Linux it only outlasts gitmelt with a clipboard manager running.
+    #[arg(long, conflicts_with_all = ["output", "stdout", "dry", "split_tokens", "gzip", "watch"])]
+    clipboard: bool,
+
+    /// Write each file to DIR/<path>.txt (or the preset's extension) with the prologue in DIR/index.txt
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "stdout", "clipboard", "dry", "count_only", "split_tokens", "manifest", "watch"])]
+    split_dir: Option<PathBuf>,
+
     /// Verbose logging (info level). Default is error only.
     #[arg(short, long)]
     verbose: bool,
 
+    /// Do not print the token summary, progress bar or other status messages
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
     /// Output preset
     #[arg(long, value_enum, default_value_t = Preset::Default)]
     preset: Preset,
 
-    /// Prologue mode (tree, list, off)
-    #[arg(long, value_enum, default_value_t = crate::decorator::PrologueMode::List)]
-    prologue: crate::decorator::PrologueMode,
+    /// Pick the preset from the output file's extension (.md, .xml, .json, .html)
+    /// unless --preset is given
+    #[arg(long)]
+    format_detect: bool,
+
+    /// Prologue mode (list, tree, full: tree and list with sizes, off)
+    #[arg(long, value_enum, default_value_t = gitmelt::decorator::PrologueMode::List)]
+    prologue: gitmelt::decorator::PrologueMode,
+
+    /// Put a `### Directory: src/ ###` header before each directory's files
+    #[arg(long)]
+    group_by_dir: bool,
+
+    /// Directory levels that make up a group with --group-by-dir (2 = src/decorator/)
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "group_by_dir", value_parser = clap::value_parser!(u16).range(1..))]
+    group_depth: u16,
+
+    /// Nest files in elements for their directories with the xml and json presets
+    #[arg(long, conflicts_with_all = ["group_by_dir", "split_dir", "template_file"])]
+    nested: bool,
+
+    /// Print the file tree to stderr and leave the prologue out of the digest
+    #[arg(long)]
+    tree_to_stderr: bool,
+
+    /// Show each listed file's size and line count in the prologue, plus a total.
+    /// With --prologue tree, show the size of every file and directory instead
+    #[arg(long)]
+    prologue_stats: bool,
 
     /// Dry run (only token estimation)
     #[arg(long)]
     dry: bool,
 
+    /// Dry run that also prints a table of tokens per top-level directory
+    #[arg(long, conflicts_with_all = ["output", "stdout", "clipboard", "watch", "no_tokens"])]
+    count_only: bool,
+
     /// Disable token counting
     #[arg(long)]
     no_tokens: bool,
 
+    /// How to handle binary files
+    #[arg(long, value_enum, default_value_t = BinaryMode::Skip)]
+    binary: BinaryMode,
+
+    /// Binary files larger than this are skipped even with --binary hexdump or base64
+    #[arg(long, value_name = "BYTES", default_value = "64KB", value_parser = ingest::parse_size)]
+    hexdump_max_size: u64,
+
+    /// Number of leading bytes shown in a hexdump
+    #[arg(long, value_name = "BYTES", default_value = "256", value_parser = ingest::parse_size)]
+    hexdump_bytes: u64,
+
+    /// Replace matches of REGEX in file content before counting or writing it (repeatable)
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    redact: Vec<Regex>,
+
+    /// Built-in redaction patterns, e.g. `aws,email`
+    #[arg(long, value_name = "PRESETS", value_enum, value_delimiter = ',')]
+    redact_preset: Vec<RedactPreset>,
+
+    /// Text that replaces redacted matches
+    #[arg(long, value_name = "TEXT", default_value = gitmelt::redact::REDACTED)]
+    redact_with: String,
+
+    /// Leave out text files whose content matches REGEX, e.g. `DO NOT EDIT` banners (repeatable)
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    exclude_matching: Vec<Regex>,
+
+    /// List files matching GLOB (path or file name) with a placeholder instead of their content (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    redact_path: Vec<String>,
+
+    /// Always read files matching this glob as text, bypassing binary detection (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    force_text: Vec<String>,
+
+    /// Number of leading bytes inspected to detect binary files (0 disables detection)
+    #[arg(long, value_name = "N", default_value_t = 1024)]
+    binary_detect_bytes: usize,
+
+    /// Split the output into digest.001.txt, digest.002.txt, ... of at most N tokens each
+    #[arg(long, value_name = "N", conflicts_with_all = ["stdout", "no_tokens"])]
+    split_tokens: Option<usize>,
+
+    /// Leave out files that would take the digest past N tokens and list the omitted ones
+    #[arg(long, value_name = "N", conflicts_with_all = ["split_tokens", "no_tokens"])]
+    token_budget: Option<usize>,
+
+    /// Exit with an error when the digest has more than N tokens (the digest is still written)
+    #[arg(long, value_name = "N", conflicts_with_all = ["no_tokens", "watch"])]
+    fail_over_tokens: Option<usize>,
+
+    /// Gzip-compress the output (appends .gz to the output file name)
+    #[arg(long)]
+    gzip: bool,
+
+    /// Add to the end of an existing output file instead of overwriting it, without a second prologue
+    #[arg(long, conflicts_with_all = ["stdout", "clipboard", "dry", "count_only", "split_tokens", "split_dir", "manifest", "watch"])]
+    append: bool,
+
+    /// Prefix each line of file content with its line number
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// Wrap content lines longer than N characters, at word boundaries where possible
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(10..))]
+    wrap_width: Option<u16>,
+
+    /// Write the contents of this file at the very start of the digest, e.g. instructions for a model
+    #[arg(long, value_name = "PATH")]
+    prepend: Option<PathBuf>,
+
+    /// Write the contents of this file at the very end of the digest, e.g. a closing question
+    #[arg(long, value_name = "PATH")]
+    append_text: Option<PathBuf>,
+
+    /// Format the digest with a template file instead of a preset (see README for placeholders)
+    #[arg(long, value_name = "PATH", conflicts_with = "preset")]
+    template_file: Option<PathBuf>,
+
+    /// Header written above each file by the default preset, with {path}, {tokens},
+    /// {lines} and {notes} placeholders and \n for line breaks
+    #[arg(long, value_name = "STRING", allow_hyphen_values = true)]
+    separator_template: Option<String>,
+
+    /// Override the markdown fence language for an extension or file name (repeatable)
+    #[arg(long, value_name = "EXT=LANG", value_parser = gitmelt::decorator::markdown::parse_lang_mapping)]
+    lang_map: Vec<(String, String)>,
+
+    /// Collapse runs of blank lines into one and trim trailing whitespace
+    #[arg(long)]
+    collapse_blank_lines: bool,
+
+    /// Remove comments from source files, chosen by extension (best-effort, heuristic)
+    #[arg(long)]
+    strip_comments: bool,
+
+    /// Pipe each file's content through a shell command and use its output
+    /// instead (the path is in GITMELT_PATH; runs once per file, see README)
+    #[arg(long, value_name = "CMD")]
+    transform_cmd: Option<String>,
+
+    /// Add last-commit author, date and short SHA to each file header (git inputs only)
+    #[arg(long)]
+    git_meta: bool,
+
+    /// Replace files identical to an earlier file with a reference to that file
+    #[arg(long)]
+    dedupe: bool,
+
+    /// With --dedupe, also treat files differing only in line endings or trailing whitespace as identical
+    #[arg(long, requires = "dedupe")]
+    dedupe_whitespace_insensitive: bool,
+
+    /// Price per 1,000 tokens in dollars, to show an estimated cost next to the token total
+    #[arg(long, value_name = "PRICE", value_parser = ingest::parse_rate, conflicts_with = "no_tokens")]
+    cost_per_1k: Option<f64>,
+
+    /// Show each file's token count in its header
+    #[arg(long, conflicts_with = "no_tokens")]
+    show_tokens: bool,
+
+    /// Show each file's line count in its header (works with --no-tokens)
+    #[arg(long, visible_alias = "count-lines")]
+    show_lines: bool,
+
+    /// Tokenizer used for token estimation (ignored with --no-tokens)
+    #[arg(long, value_enum, default_value_t = TokenModel::Cl100k)]
+    token_model: TokenModel,
+
+    /// Skip files larger than this size (e.g. 500KB, 25MB). 0 means no limit
+    #[arg(long, default_value = "10MB", value_parser = ingest::parse_size)]
+    max_file_size: u64,
+
+    /// Leave out files smaller than this size (e.g. 1, 100B) during traversal
+    #[arg(long, value_name = "BYTES", default_value = "0", value_parser = ingest::parse_size)]
+    min_file_size: u64,
+
+    /// Include empty files, which are skipped by default
+    #[arg(long)]
+    include_empty: bool,
+
+    /// Only include files added or modified since REF (git diff REF...HEAD); inputs must be git repositories
+    #[arg(long, value_name = "REF")]
+    only_changed: Option<String>,
+
+    /// Only include files modified within DURATION, e.g. 12h, 7d or 2w
+    #[arg(long, value_name = "DURATION", value_parser = traversal::parse_duration)]
+    since: Option<Duration>,
+
+    /// Keep the first BYTES of oversized files instead of skipping them (e.g. 64KB)
+    #[arg(long, value_name = "BYTES", value_parser = ingest::parse_size)]
+    truncate_large: Option<u64>,
+
+    /// Apply --truncate-large to every file, not only those over --max-file-size
+    #[arg(long, requires = "truncate_large")]
+    truncate_all: bool,
+
+    /// Cut each file's content after N tokens (no effect with --no-tokens)
+    #[arg(long, value_name = "N")]
+    max_tokens_per_file: Option<usize>,
+
+    /// Read files larger than BYTES one at a time, in digest order, to bound memory use (e.g. 1MB)
+    #[arg(long, value_name = "BYTES", value_parser = ingest::parse_size)]
+    defer_large: Option<u64>,
+
+    /// Read defaults from this config file instead of discovering .gitmelt.toml
+    #[arg(long, value_name = "PATH", conflicts_with = "no_config")]
+    config: Option<PathBuf>,
+
+    /// Do not look for a .gitmelt.toml config file
+    #[arg(long)]
+    no_config: bool,
+
+    /// Number of threads used to process files (default: all cores)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// Do not show a progress bar (it is also hidden when stderr is not a terminal or with --verbose)
+    #[arg(long)]
+    no_progress: bool,
+
     /// Show detailed timing information
     #[arg(short, long)]
     timing: bool,
+
+    /// Show paths in the digest relative to DIR, which must contain every file
+    #[arg(long, value_name = "DIR")]
+    relative_to: Option<PathBuf>,
+
+    /// Write a TSV of each file's byte offset and length in the digest to PATH
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["split_tokens", "dry", "count_only"])]
+    manifest: Option<PathBuf>,
+
+    /// List every skipped file (binary, oversized, unreadable, duplicate) at the end of the digest
+    #[arg(long)]
+    skip_summary: bool,
+
+    /// Separate sections by exactly one blank line and end the digest with one newline
+    #[arg(long)]
+    normalize_spacing: bool,
+
+    /// Write run metrics as JSON to PATH ("-" for stderr)
+    #[arg(long, value_name = "PATH")]
+    metrics_json: Option<PathBuf>,
+
+    /// Keep running and regenerate the digest whenever an included file changes
+    #[arg(long, conflicts_with_all = ["stdout", "dry"])]
+    watch: bool,
+
+    /// Pick the files to include from a checklist before ingesting
+    #[arg(long, conflicts_with = "watch")]
+    interactive: bool,
+}
+
+/// Preset matching the extension of an output path, looking through `.gz`
+fn preset_for_path(path: &Path) -> Option<Preset> {
+    let mut extension = path.extension()?;
+    if extension.eq_ignore_ascii_case("gz") {
+        extension = Path::new(path.file_stem()?).extension()?;
+    }
+    match extension.to_ascii_lowercase().to_str()? {
+        "md" | "markdown" => Some(Preset::Markdown),
+        "xml" => Some(Preset::Xml),
+        "json" => Some(Preset::Json),
+        "html" | "htm" => Some(Preset::Html),
+        _ => None,
+    }
+}
+
+/// Content decorator for a built-in preset
+fn preset_decorator(cli: &Cli) -> Box<dyn ContentDecorator> {
+    match cli.preset {
+        Preset::Default => Box::new(DefaultDecorator {
+            show_tokens: cli.show_tokens,
+            show_lines: cli.show_lines,
+            template: cli.separator_template.clone(),
+        }),
+        Preset::Markdown => Box::new(MarkdownDecorator {
+            show_tokens: cli.show_tokens,
+            show_lines: cli.show_lines,
+            lang_map: cli.lang_map.iter().cloned().collect(),
+        }),
+        Preset::Xml => Box::new(XmlDecorator {
+            show_tokens: cli.show_tokens,
+            show_lines: cli.show_lines,
+        }),
+        Preset::Json => Box::new(JsonDecorator),
+        Preset::Html => Box::new(HtmlDecorator {
+            show_tokens: cli.show_tokens,
+            show_lines: cli.show_lines,
+        }),
+    }
+}
+
+/// Prints the --count-only table, largest directories first
+#[allow(clippy::cast_precision_loss)]
+fn print_token_breakdown(metrics: &IngestMetrics) {
+    let mut groups: Vec<_> = metrics.directory_tokens.iter().collect();
+    groups.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let total: usize = groups.iter().map(|(_, tokens)| **tokens).sum();
+    let width = groups.iter().map(|(dir, _)| dir.len()).max().unwrap_or(0);
+
+    println!("Tokens by directory:");
+    for (dir, tokens) in groups {
+        let share = if total == 0 {
+            0.0
+        } else {
+            *tokens as f64 * 100.0 / total as f64
+        };
+        println!(
+            "  {dir:<width$}  {:>12}  {share:5.1}%",
+            format_count(*tokens)
+        );
+    }
+}
+
+/// Prints the file tree for --tree-to-stderr, for the user rather than the digest
+fn print_tree(
+    roots: &[TreeRoot],
+    files: &[PathBuf],
+    options: &IngestOptions,
+    stats: bool,
+) -> Result<()> {
+    let shown = files
+        .iter()
+        .map(|file| ingest::display_path(options, file))
+        .collect::<Result<Vec<_>>>()?;
+    let tree = FileTreeDecorator {
+        roots: roots.to_vec(),
+        mode: PrologueMode::Tree,
+        stats,
+        max_file_size: options.max_file_size,
+    };
+    if let Some(tree) = tree.prologue(&shown) {
+        eprint!("{tree}");
+    }
+    Ok(())
+}
+
+/// Serializes the run metrics and timings for --metrics-json
+fn metrics_json(
+    metrics: &IngestMetrics,
+    discovery: Duration,
+    ingestion: Duration,
+    total: Duration,
+) -> serde_json::Value {
+    serde_json::json!({
+        "total_tokens": metrics.total_tokens,
+        "total_bytes": metrics.total_bytes,
+        "total_lines": metrics.total_lines,
+        "file_count": metrics.file_count,
+        "skipped_binary": metrics.skipped_binary,
+        "skipped_large": metrics.skipped_large,
+        "excluded": metrics.excluded,
+        "prefetched": metrics.prefetched,
+        "durations_secs": {
+            "discovery": discovery.as_secs_f64(),
+            "ingestion": ingestion.as_secs_f64(),
+            "total": total.as_secs_f64(),
+        },
+    })
 }
 
 fn init_logger(verbose: bool) {
@@ -88,88 +582,571 @@ fn init_logger(verbose: bool) {
     };
 
     builder.filter_level(level);
+    // Warnings about the digest itself, such as files left out, always show
+    builder.filter_module(gitmelt::NOTICE_TARGET, level.max(LevelFilter::Warn));
     builder.init();
 }
 
+/// Fills in config file values for every option not given on the command line
+fn apply_config(cli: &mut Cli, matches: &ArgMatches, config: Config) -> Result<()> {
+    let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let invalid = |key: &str, value: &str| {
+        anyhow::anyhow!("Invalid value '{value}' for '{key}' in config file")
+    };
+
+    if let Some(preset) = config.preset
+        && !from_cli("preset")
+    {
+        cli.preset = Preset::from_str(&preset, true).map_err(|_| invalid("preset", &preset))?;
+    }
+    if let Some(prologue) = config.prologue
+        && !from_cli("prologue")
+    {
+        cli.prologue = gitmelt::decorator::PrologueMode::from_str(&prologue, true)
+            .map_err(|_| invalid("prologue", &prologue))?;
+    }
+    if let Some(token_model) = config.token_model
+        && !from_cli("token_model")
+    {
+        cli.token_model = TokenModel::from_str(&token_model, true)
+            .map_err(|_| invalid("token-model", &token_model))?;
+    }
+    if let Some(include) = config.include
+        && !from_cli("include")
+    {
+        cli.include = include;
+    }
+    if let Some(exclude) = config.exclude
+        && !from_cli("exclude")
+    {
+        cli.exclude = exclude;
+    }
+    // An explicit --output-name replaces the config file's output path too
+    if let Some(output) = config.output
+        && !from_cli("output")
+        && !from_cli("output_name")
+    {
+        cli.output = Some(output);
+    }
+    if let Some(output_name) = config.output_name
+        && !from_cli("output_name")
+    {
+        cli.output_name = output_name;
+    }
+    if let Some(no_tokens) = config.no_tokens
+        && !from_cli("no_tokens")
+    {
+        cli.no_tokens = no_tokens;
+    }
+    if let Some(show_tokens) = config.show_tokens
+        && !from_cli("show_tokens")
+    {
+        cli.show_tokens = show_tokens;
+    }
+    if let Some(show_lines) = config.show_lines
+        && !from_cli("show_lines")
+    {
+        cli.show_lines = show_lines;
+    }
+
+    // Command-line mappings come last so they win over the config file's
+    if let Some(lang_map) = config.lang_map {
+        let mut merged: Vec<_> = lang_map.into_iter().collect();
+        merged.append(&mut cli.lang_map);
+        cli.lang_map = merged;
+    }
+
+    if cli.show_tokens && cli.no_tokens {
+        anyhow::bail!("show-tokens cannot be used together with no-tokens");
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let global_start = Instant::now();
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     init_logger(cli.verbose);
 
-    let temp_dir_handle = if cli.input.starts_with("http") || cli.input.starts_with("git@") {
-        Some(cloner::clone_repo(&cli.input, cli.branch.as_deref())?)
-    } else {
+    let config_path = if cli.no_config {
         None
-    };
-
-    let root_path = if let Some(ref temp) = temp_dir_handle {
-        temp.path().to_path_buf()
+    } else if let Some(path) = &cli.config {
+        Some(path.clone())
     } else {
-        PathBuf::from(&cli.input)
+        config::discover(&env::current_dir()?)
     };
 
-    let options = TraversalOptions {
-        root: root_path.clone(),
-        include: cli.include,
-        exclude: cli.exclude,
-    };
+    if let Some(path) = config_path {
+        info!("Using config file {}", path.display());
+        apply_config(&mut cli, &matches, Config::load(&path)?)?;
+    }
 
-    info!("Traversing files in {}", options.root.display());
-    let discovery_start = Instant::now();
-    let files = traversal::traverse(&options)?;
-    let discovery_duration = discovery_start.elapsed();
+    // `--output -` means stdout, as in other command line tools
+    if cli.output.as_deref() == Some(Path::new("-")) {
+        cli.output = None;
+        cli.stdout = true;
+        let stdout_conflicts = [
+            ("--split-tokens", cli.split_tokens.is_some()),
+            ("--append", cli.append),
+            ("--watch", cli.watch),
+        ];
+        for (flag, set) in stdout_conflicts {
+            if set {
+                Cli::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        format!("{flag} cannot be used with '--output -'"),
+                    )
+                    .exit();
+            }
+        }
+    }
 
-    info!("Found {} files", files.len());
-    if files.is_empty() {
-        info!("No files found matching patterns.");
-        return Ok(());
+    // An explicit --preset, or a preset set in the config file, wins
+    if cli.format_detect
+        && matches!(cli.preset, Preset::Default)
+        && matches.value_source("preset") != Some(ValueSource::CommandLine)
+        && cli.template_file.is_none()
+        && let Some(preset) =
+            preset_for_path(cli.output.as_deref().unwrap_or(Path::new(&cli.output_name)))
+    {
+        info!(
+            "Using the {} preset for the output's extension",
+            preset
+                .to_possible_value()
+                .expect("no skipped variants")
+                .get_name()
+        );
+        cli.preset = preset;
+    }
+
+    if cli.nested && !matches!(cli.preset, Preset::Xml | Preset::Json) {
+        anyhow::bail!("--nested only works with the xml and json presets");
+    }
+    // Their closing tags or brackets are already at the end of the file
+    if cli.append && matches!(cli.preset, Preset::Xml | Preset::Json | Preset::Html) {
+        anyhow::bail!("--append only works with the default and markdown presets");
+    }
+    // Secrets in a hex dump or base64 are out of reach of the patterns
+    if (!cli.redact.is_empty() || !cli.redact_preset.is_empty()) && cli.binary != BinaryMode::Skip {
+        anyhow::bail!(
+            "--binary hexdump and base64 cannot be used with --redact or --redact-preset"
+        );
+    }
+
+    let mut include = std::mem::take(&mut cli.include);
+    let mut exclude = std::mem::take(&mut cli.exclude);
+    if let Some(path) = &cli.patterns_file {
+        traversal::read_patterns_file(path, &mut include, &mut exclude)?;
+    }
+    include.extend(
+        cli.include_ext
+            .iter()
+            .map(|ext| traversal::extension_glob(ext)),
+    );
+    exclude.extend(
+        cli.exclude_ext
+            .iter()
+            .map(|ext| traversal::extension_glob(ext)),
+    );
+
+    let clone_options = CloneOptions {
+        branch: cli.branch.clone(),
+        subdir: cli.subdir.clone(),
+        rev: cli.rev.clone(),
+        token: cli.token.clone(),
+        ssh_key: cli.ssh_key.clone(),
+        submodules: cli.submodules,
+        full_clone: cli.full_clone,
+        cache_dir: if cli.no_cache {
+            None
+        } else {
+            cli.cache_dir
+                .clone()
+                .or_else(|| dirs::cache_dir().map(|dir| dir.join("gitmelt")))
+        },
+        timeout: cli.clone_timeout.map(Duration::from_secs),
+        retries: cli.clone_retries,
+    };
+
+    if cli.watch
+        && cli
+            .input
+            .iter()
+            .any(|input| cloner::is_git_url(input) || archive::is_archive(input))
+    {
+        anyhow::bail!("--watch only works with local inputs");
     }
 
-    info!("Generating digest...");
+    let discovery_start = Instant::now();
+    // Clones of URL inputs must outlive ingestion (temp dirs are deleted on drop)
+    let inputs = gitmelt::resolve_inputs(&cli.input, &clone_options)?;
 
-    let output_dest = if cli.dry {
+    let traversal_options = TraversalOptions {
+        root: PathBuf::new(),
+        include,
+        exclude,
+        exclude_dirs: cli.exclude_dir.clone(),
+        follow_symlinks: cli.follow_symlinks,
+        max_depth: cli.max_depth,
+        sort: cli.sort,
+        reverse: cli.reverse,
+        no_gitignore: cli.no_gitignore,
+        apply_gitignore_always: cli.apply_gitignore_always,
+        ignore_files: cli.ignore_files,
+        no_ignore: cli.no_ignore,
+        hidden: cli.hidden,
+        glob_case_insensitive: cli.glob_case_insensitive,
+        max_files: cli.max_files,
+        min_file_size: cli.min_file_size,
+        skip_empty: !cli.include_empty,
+        only_changed: cli.only_changed.clone(),
+        since: cli.since,
+        concat_order: cli
+            .concat_order
+            .as_deref()
+            .map(traversal::read_order_file)
+            .transpose()?
+            .unwrap_or_default(),
+        strict: cli.strict,
+    };
+    let output_path = cli.output.clone().unwrap_or_else(|| {
+        let repo = cli
+            .input
+            .iter()
+            .map(|input| gitmelt::input_name(input))
+            .collect::<Vec<_>>()
+            .join("-");
+        env::current_dir()
+            .unwrap()
+            .join(ingest::output_name(&cli.output_name, &repo))
+    });
+    let output_dest = if cli.dry || cli.count_only {
         OutputDestination::Null
     } else if cli.stdout {
         OutputDestination::Stdout
+    } else if cli.clipboard {
+        OutputDestination::Buffer
+    } else if let Some(dir) = &cli.split_dir {
+        OutputDestination::Directory(dir.clone())
     } else {
-        let path = cli
-            .output
-            .unwrap_or_else(|| env::current_dir().unwrap().join(ingest::DIGEST_FILENAME));
-        OutputDestination::File(path)
+        OutputDestination::File(output_path.clone())
     };
 
-    let content_decorator: Box<dyn ContentDecorator> = match cli.preset {
-        Preset::Default => Box::new(DefaultDecorator),
-        Preset::Markdown => Box::new(MarkdownDecorator),
-        Preset::Xml => Box::new(XmlDecorator),
+    let template = cli
+        .template_file
+        .as_deref()
+        .map(Template::load)
+        .transpose()?;
+    let mut content_decorator: Box<dyn ContentDecorator> = match &template {
+        Some(template) => Box::new(TemplateDecorator {
+            template: template.clone(),
+        }),
+        None => preset_decorator(&cli),
     };
 
+    // Inside the line numbers, so continuation lines are not numbered
+    if let Some(width) = cli.wrap_width {
+        content_decorator = Box::new(WrapDecorator {
+            inner: content_decorator,
+            width: usize::from(width),
+            numbered: cli.line_numbers,
+        });
+    }
+
+    if cli.line_numbers {
+        content_decorator = Box::new(LineNumberDecorator {
+            inner: content_decorator,
+        });
+    }
+
+    if cli.collapse_blank_lines {
+        content_decorator = Box::new(CollapseBlankLinesDecorator {
+            inner: content_decorator,
+        });
+    }
+
+    // Outermost, so comments are gone before blank lines are collapsed and
+    // lines are numbered
+    if cli.strip_comments {
+        content_decorator = Box::new(StripCommentsDecorator {
+            inner: content_decorator,
+        });
+    }
+
+    // Outside everything else, so the command sees the file as it was read
+    if let Some(command) = &cli.transform_cmd {
+        content_decorator = Box::new(TransformCommandDecorator {
+            inner: content_decorator,
+            command: command.clone(),
+        });
+    }
+
+    let relative_to = cli
+        .relative_to
+        .as_deref()
+        .map(|dir| {
+            dir.canonicalize()
+                .with_context(|| format!("Failed to resolve --relative-to {}", dir.display()))
+        })
+        .transpose()?;
     let global_decorator = FileTreeDecorator {
-        root: options.root.clone(),
-        mode: cli.prologue,
+        // With --relative-to the decorators see paths that are already relative
+        roots: match &relative_to {
+            Some(_) => vec![TreeRoot {
+                label: ".".to_string(),
+                path: PathBuf::new(),
+            }],
+            None => inputs.roots.clone(),
+        },
+        mode: if cli.tree_to_stderr {
+            PrologueMode::Off
+        } else {
+            cli.prologue
+        },
+        stats: cli.prologue_stats,
+        max_file_size: cli.max_file_size,
+    };
+    let template_prologue = template.map(|template| TemplatePrologue {
+        template,
+        inner: &global_decorator,
+    });
+    let prologue_decorator: &dyn GlobalDecorator = match &template_prologue {
+        Some(template_prologue) => template_prologue,
+        None => &global_decorator,
     };
 
-    let ingest_start = Instant::now();
-    let _ingest_metrics = ingest::ingest(
-        &files,
+    // Checked once per root: shelling out to git per file is wasted work
+    // outside a repository
+    let git_meta_roots: Vec<PathBuf> = if cli.git_meta {
+        inputs
+            .roots
+            .iter()
+            .filter(|root| cloner::is_work_tree(&root.path))
+            .map(|root| root.path.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if cli.git_meta && git_meta_roots.is_empty() {
+        warn!("--git-meta ignored: no input is a git repository");
+    }
+    if cli.max_tokens_per_file.is_some() && cli.no_tokens {
+        warn!("--max-tokens-per-file ignored: --no-tokens leaves no tokenizer to cut with");
+    }
+
+    let read_text = |path: &Option<PathBuf>| {
+        path.as_deref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))
+            })
+            .transpose()
+    };
+
+    let ingest_options = IngestOptions {
+        count_tokens: !cli.no_tokens,
+        token_model: cli.token_model,
+        max_file_size: cli.max_file_size,
+        truncate_large: cli.truncate_large,
+        truncate_all: cli.truncate_all,
+        defer_large: cli.defer_large,
+        max_tokens_per_file: cli.max_tokens_per_file,
+        split_tokens: cli.split_tokens,
+        token_budget: cli.token_budget,
+        gzip: cli.gzip,
+        append: cli.append,
+        threads: cli.threads.map(usize::from),
+        binary: cli.binary,
+        hexdump_max_size: cli.hexdump_max_size,
+        hexdump_bytes: cli.hexdump_bytes,
+        redact: cli
+            .redact
+            .iter()
+            .cloned()
+            .chain(cli.redact_preset.iter().map(|preset| preset.regex()))
+            .collect(),
+        redact_with: cli.redact_with.clone(),
+        exclude_matching: cli.exclude_matching.clone(),
+        redact_paths: cli.redact_path.clone(),
+        force_text: cli.force_text.clone(),
+        binary_detect_bytes: cli.binary_detect_bytes,
+        git_meta_roots,
+        dedupe: cli.dedupe,
+        dedupe_ignore_whitespace: cli.dedupe_whitespace_insensitive,
+        progress: !cli.no_progress && !cli.verbose && !cli.quiet && io::stderr().is_terminal(),
+        quiet: cli.quiet,
+        cost_per_1k: cli.cost_per_1k,
+        strict: cli.strict,
+        group_by_dir: cli
+            .group_by_dir
+            .then(|| (global_decorator.roots.clone(), usize::from(cli.group_depth))),
+        nested: cli.nested.then(|| global_decorator.roots.clone()),
+        token_breakdown: cli.count_only.then(|| global_decorator.roots.clone()),
+        split_roots: cli
+            .split_dir
+            .is_some()
+            .then(|| global_decorator.roots.clone()),
+        relative_to,
+        manifest: cli.manifest.clone(),
+        skip_summary: cli.skip_summary,
+        normalize_spacing: cli.normalize_spacing,
+        prepend: read_text(&cli.prepend)?,
+        append_text: read_text(&cli.append_text)?,
+    };
+
+    // Files are read and tokenized while the walk is still finding more
+    let mut discovery_end = None;
+    let mut ingest_start = None;
+    let (files, mut ingest_metrics) = ingest::ingest_streaming(
+        |on_file| {
+            // Nothing is read ahead when the user may still drop files
+            let on_file: &(dyn Fn(&Path) + Sync) = if cli.interactive {
+                &|_: &Path| {}
+            } else {
+                on_file
+            };
+            let files = match &cli.files_from {
+                // Listed paths are relative to the working directory, the default input
+                Some(list) => traversal::filter_files(
+                    &TraversalOptions {
+                        root: PathBuf::from("."),
+                        ..traversal_options.clone()
+                    },
+                    traversal::read_file_list(list, Path::new("."))?,
+                ),
+                None => gitmelt::collect_files_with(&traversal_options, &inputs.roots, on_file),
+            };
+            discovery_end = Some(Instant::now());
+            let files = match files {
+                Ok(files) if cli.interactive && !files.is_empty() => {
+                    match picker::pick(files, &global_decorator.roots)? {
+                        Some(files) => Ok(files),
+                        None => anyhow::bail!("Cancelled, no digest written"),
+                    }
+                }
+                files => files,
+            };
+            ingest_start = Some(Instant::now());
+            if cli.tree_to_stderr
+                && let Ok(files) = &files
+            {
+                print_tree(
+                    &global_decorator.roots,
+                    files,
+                    &ingest_options,
+                    cli.prologue_stats,
+                )?;
+            }
+            files
+        },
         output_dest,
         content_decorator.as_ref(),
-        Some(&global_decorator),
-        !cli.no_tokens,
+        Some(prologue_decorator),
+        &ingest_options,
     )?;
-    let ingest_duration = ingest_start.elapsed();
+    let discovery_end = discovery_end.unwrap_or_else(Instant::now);
+    let discovery_duration = discovery_end - discovery_start;
+    let ingest_duration = ingest_start.unwrap_or(discovery_end).elapsed();
+
+    if files.is_empty() {
+        info!("No files found matching patterns.");
+        if !cli.watch {
+            return Ok(());
+        }
+    }
+
+    if let Some(digest) = ingest_metrics.as_mut().and_then(|m| m.output.take()) {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(digest.as_str()))
+            .context("Failed to copy the digest to the clipboard")?;
+        if !cli.quiet {
+            println!("Copied {} bytes to clipboard", format_count(digest.len()));
+        }
+    }
 
     info!("Done!");
 
+    if cli.count_only
+        && let Some(metrics) = &ingest_metrics
+    {
+        print_token_breakdown(metrics);
+    }
+
     if cli.timing {
         println!("\nTiming Summary:");
         println!("----------------------------------------");
         println!("Discovery:      {discovery_duration:?}");
         println!("Ingestion:      {ingest_duration:?}");
+        if let Some(metrics) = &ingest_metrics {
+            println!(
+                "Ingested:       {} files, {} bytes",
+                metrics.file_count, metrics.total_bytes
+            );
+            println!(
+                "Overlapped:     {} files processed during discovery",
+                metrics.prefetched
+            );
+        }
         println!("Total Runtime:  {:?}", global_start.elapsed());
         println!("----------------------------------------");
     }
 
+    if let (Some(dest), Some(metrics)) = (&cli.metrics_json, &ingest_metrics) {
+        let json = metrics_json(
+            metrics,
+            discovery_duration,
+            ingest_duration,
+            global_start.elapsed(),
+        );
+        let json = serde_json::to_string_pretty(&json)?;
+        if dest.as_os_str() == "-" {
+            eprintln!("{json}");
+        } else {
+            std::fs::write(dest, json + "\n")
+                .with_context(|| format!("Failed to write metrics to {}", dest.display()))?;
+        }
+    }
+
+    // Checked last, so the digest and metrics are there to inspect
+    if let (Some(limit), Some(metrics)) = (cli.fail_over_tokens, &ingest_metrics)
+        && metrics.total_tokens > limit
+    {
+        anyhow::bail!(
+            "The digest has {} tokens, more than the {} allowed by --fail-over-tokens",
+            format_count(metrics.total_tokens),
+            format_count(limit)
+        );
+    }
+
+    if cli.watch {
+        let watched: Vec<PathBuf> = inputs.roots.iter().map(|root| root.path.clone()).collect();
+        let sidecars: Vec<PathBuf> = cli.manifest.iter().cloned().collect();
+        let mut previous = watch::snapshot(&files, &output_path, &sidecars);
+
+        watch::watch(&watched, || {
+            let files = gitmelt::collect_files(&traversal_options, &inputs.roots)?;
+            let current = watch::snapshot(&files, &output_path, &sidecars);
+            if current == previous {
+                return Ok(());
+            }
+            previous = current;
+
+            ingest::ingest(
+                &files,
+                OutputDestination::File(output_path.clone()),
+                content_decorator.as_ref(),
+                Some(prologue_decorator),
+                &ingest_options,
+            )?;
+            if !cli.quiet {
+                println!("Regenerated ({} files)", files.len());
+            }
+            Ok(())
+        })?;
+    }
+
     Ok(())
 }
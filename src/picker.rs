@@ -0,0 +1,295 @@
+use anyhow::{Result, bail};
+use gitmelt::decorator::file_tree::display_path;
+use gitmelt::decorator::{TreeRoot, format_human_size};
+use ratatui::Terminal;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{List, ListItem, ListState};
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+/// Rows moved by PageUp and PageDown
+const PAGE: usize = 20;
+
+struct Item {
+    path: PathBuf,
+    label: String,
+    size: u64,
+    selected: bool,
+}
+
+/// Selection state of the checklist, kept apart from the terminal so it can be tested
+struct Picker {
+    items: Vec<Item>,
+    /// Case-insensitive substring the shown paths must contain
+    filter: String,
+    /// Whether keys are currently typed into the filter
+    filtering: bool,
+    /// Position of the highlighted row among the visible items
+    cursor: usize,
+}
+
+impl Picker {
+    fn new(files: Vec<PathBuf>, roots: &[TreeRoot]) -> Self {
+        let items = files
+            .into_iter()
+            .map(|path| Item {
+                label: display_path(roots, &path),
+                size: std::fs::metadata(&path).map_or(0, |m| m.len()),
+                path,
+                selected: true,
+            })
+            .collect();
+        Self {
+            items,
+            filter: String::new(),
+            filtering: false,
+            cursor: 0,
+        }
+    }
+
+    /// Indices of the items matching the filter
+    fn visible(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        (0..self.items.len())
+            .filter(|&i| self.items[i].label.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn toggle(&mut self) {
+        if let Some(&i) = self.visible().get(self.cursor) {
+            self.items[i].selected = !self.items[i].selected;
+        }
+    }
+
+    /// Flips every visible item, leaving those hidden by the filter alone
+    fn invert(&mut self) {
+        for i in self.visible() {
+            self.items[i].selected = !self.items[i].selected;
+        }
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.visible().len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.cursor = 0;
+    }
+
+    fn selected_count(&self) -> usize {
+        self.items.iter().filter(|item| item.selected).count()
+    }
+
+    fn into_selected(self) -> Vec<PathBuf> {
+        self.items
+            .into_iter()
+            .filter(|item| item.selected)
+            .map(|item| item.path)
+            .collect()
+    }
+
+    /// Applies a key press. Returns `Some(true)` to confirm, `Some(false)` to cancel.
+    fn handle(&mut self, key: KeyEvent) -> Option<bool> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(false);
+        }
+        if self.filtering {
+            match key.code {
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filtering = false;
+                    self.set_filter(String::new());
+                }
+                KeyCode::Backspace => {
+                    let mut filter = self.filter.clone();
+                    filter.pop();
+                    self.set_filter(filter);
+                }
+                KeyCode::Char(c) => {
+                    let filter = format!("{}{c}", self.filter);
+                    self.set_filter(filter);
+                }
+                _ => {}
+            }
+            return None;
+        }
+        match key.code {
+            KeyCode::Enter => return Some(true),
+            KeyCode::Esc | KeyCode::Char('q') => return Some(false),
+            KeyCode::Char(' ') => self.toggle(),
+            KeyCode::Char('a' | 'i') => self.invert(),
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-(PAGE as isize)),
+            KeyCode::PageDown => self.move_cursor(PAGE as isize),
+            KeyCode::Home | KeyCode::Char('g') => self.cursor = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_cursor(isize::MAX),
+            _ => {}
+        }
+        None
+    }
+
+    fn status(&self) -> String {
+        if self.filtering {
+            format!("/{}", self.filter)
+        } else {
+            let filter = if self.filter.is_empty() {
+                String::new()
+            } else {
+                format!(" matching \"{}\"", self.filter)
+            };
+            format!(
+                "{} of {} selected{filter} | space toggle, a invert, / filter, enter confirm, q cancel",
+                self.selected_count(),
+                self.items.len()
+            )
+        }
+    }
+}
+
+/// Shows a checklist of `files` on the terminal, all selected at first, and
+/// returns the files left selected, or `None` if the user cancelled
+pub fn pick(files: Vec<PathBuf>, roots: &[TreeRoot]) -> Result<Option<Vec<PathBuf>>> {
+    if !io::stderr().is_terminal() {
+        bail!("--interactive needs a terminal");
+    }
+    let mut picker = Picker::new(files, roots);
+
+    enable_raw_mode()?;
+    execute!(io::stderr(), EnterAlternateScreen)?;
+    let result = Terminal::new(CrosstermBackend::new(io::stderr()))
+        .map_err(anyhow::Error::from)
+        .and_then(|mut terminal| run(&mut terminal, &mut picker));
+    // Restore the terminal even if drawing failed
+    execute!(io::stderr(), LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+
+    Ok(result?.then(|| picker.into_selected()))
+}
+
+fn run<B: Backend>(terminal: &mut Terminal<B>, picker: &mut Picker) -> Result<bool>
+where
+    B::Error: Send + Sync + 'static,
+{
+    let mut state = ListState::default();
+    loop {
+        let visible = picker.visible();
+        state.select((!visible.is_empty()).then_some(picker.cursor));
+        terminal.draw(|frame| {
+            let [list_area, status_area] =
+                Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+            let rows = visible.iter().map(|&i| {
+                let item = &picker.items[i];
+                let mark = if item.selected { "[x]" } else { "[ ]" };
+                ListItem::new(format!(
+                    "{mark} {} ({})",
+                    item.label,
+                    format_human_size(item.size)
+                ))
+            });
+            let list = List::new(rows)
+                .highlight_style(Style::new().reversed())
+                .highlight_symbol("> ");
+            frame.render_stateful_widget(list, list_area, &mut state);
+            frame.render_widget(Line::from(picker.status()).bold(), status_area);
+        })?;
+
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && let Some(confirmed) = picker.handle(key)
+        {
+            return Ok(confirmed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker(names: &[&str]) -> Picker {
+        Picker::new(names.iter().map(PathBuf::from).collect(), &[])
+    }
+
+    fn press(picker: &mut Picker, code: KeyCode) -> Option<bool> {
+        picker.handle(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(picker: &mut Picker, text: &str) {
+        for c in text.chars() {
+            press(picker, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_all_selected_by_default() {
+        let picker = picker(&["a.rs", "b.rs"]);
+        assert_eq!(picker.selected_count(), 2);
+        assert_eq!(
+            picker.into_selected(),
+            vec![PathBuf::from("a.rs"), PathBuf::from("b.rs")]
+        );
+    }
+
+    #[test]
+    fn test_toggle_and_invert() {
+        let mut picker = picker(&["a.rs", "b.rs", "c.rs"]);
+        press(&mut picker, KeyCode::Down);
+        press(&mut picker, KeyCode::Char(' '));
+        assert_eq!(picker.selected_count(), 2);
+
+        press(&mut picker, KeyCode::Char('a'));
+        assert_eq!(picker.into_selected(), vec![PathBuf::from("b.rs")]);
+    }
+
+    #[test]
+    fn test_filter_limits_invert_and_toggle() {
+        let mut picker = picker(&["src/main.rs", "tests/cli.rs", "src/lib.rs", "README.md"]);
+        press(&mut picker, KeyCode::Char('/'));
+        type_text(&mut picker, "SRC");
+        press(&mut picker, KeyCode::Enter);
+        assert_eq!(picker.visible(), vec![0, 2]);
+
+        // Only the two matching files are deselected
+        press(&mut picker, KeyCode::Char('i'));
+        assert_eq!(picker.selected_count(), 2);
+
+        // The cursor stays on the filtered rows
+        press(&mut picker, KeyCode::End);
+        press(&mut picker, KeyCode::Char(' '));
+        assert_eq!(
+            picker.into_selected(),
+            vec![
+                PathBuf::from("tests/cli.rs"),
+                PathBuf::from("src/lib.rs"),
+                PathBuf::from("README.md")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escape_clears_filter_then_cancels() {
+        let mut picker = picker(&["a.rs", "b.rs"]);
+        press(&mut picker, KeyCode::Char('/'));
+        type_text(&mut picker, "b");
+        assert_eq!(picker.visible(), vec![1]);
+        // Keys typed into the filter do not act as commands
+        assert_eq!(press(&mut picker, KeyCode::Char('q')), None);
+
+        assert_eq!(press(&mut picker, KeyCode::Esc), None);
+        assert_eq!(picker.visible(), vec![0, 1]);
+        assert_eq!(press(&mut picker, KeyCode::Esc), Some(false));
+        assert_eq!(press(&mut picker, KeyCode::Enter), Some(true));
+    }
+}
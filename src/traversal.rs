@@ -1,103 +1,595 @@
-use anyhow::Result;
-use ignore::WalkBuilder;
-use ignore::overrides::OverrideBuilder;
+use crate::cloner;
+use crate::decorator::format_path;
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{DirEntry, WalkBuilder};
 use rayon::prelude::*;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
 
+/// Tool-specific ignore file with gitignore syntax, discovered in every directory
+pub const IGNORE_FILENAME: &str = ".gitmeltignore";
+
+/// Order of files in the digest
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    /// Lexicographic by relative path
+    #[default]
+    Path,
+    /// Largest files first
+    Size,
+    /// Most recently modified first
+    Mtime,
+}
+
+#[derive(Clone, Default)]
 pub struct TraversalOptions {
     pub root: PathBuf,
     pub include: Vec<String>,
     pub exclude: Vec<String>,
+    /// Directories the walk never enters: a name such as `node_modules`
+    /// matches at any depth, a path with `/` is relative to the root
+    pub exclude_dirs: Vec<String>,
+    /// Follow symbolic links to files and directories
+    pub follow_symlinks: bool,
+    /// Maximum recursion depth, where 1 means only the root's direct files
+    pub max_depth: Option<usize>,
+    pub sort: SortOrder,
+    /// Invert the sort order
+    pub reverse: bool,
+    /// Do not apply `.gitignore`, `.git/info/exclude` or the global git ignore file
+    pub no_gitignore: bool,
+    /// Apply `.gitignore` files even outside a git repository
+    pub apply_gitignore_always: bool,
+    /// Apply generic `.ignore` files
+    pub ignore_files: bool,
+    /// Do not apply any ignore files, including `.gitmeltignore`
+    pub no_ignore: bool,
+    /// Include hidden files and directories (the `.git` directory is always skipped)
+    pub hidden: bool,
+    /// Match include and exclude patterns case-insensitively
+    pub glob_case_insensitive: bool,
+    /// Stop the walk after collecting this many files. Which files are
+    /// collected depends on walk order, so sorting only orders that subset.
+    pub max_files: Option<usize>,
+    /// Leave out files smaller than this many bytes. `0` means no minimum.
+    pub min_file_size: u64,
+    /// Leave out empty files, like files below `min_file_size`
+    pub skip_empty: bool,
+    /// Keep only files added or modified relative to this git ref
+    /// (`git diff <ref>...HEAD`)
+    pub only_changed: Option<String>,
+    /// Keep only files modified within this long before the traversal
+    pub since: Option<Duration>,
+    /// Globs in priority order: files matching an earlier one come first,
+    /// unmatched files last. Overrides `sort`, whose order is kept among ties.
+    pub concat_order: Vec<String>,
+    /// Fail on walk errors (unreadable directories, broken links) instead of
+    /// logging and skipping them
+    pub strict: bool,
+}
+
+/// Appends patterns from a patterns file to the include/exclude lists.
+///
+/// Each line is `+pattern` (include) or `-pattern` (exclude); blank lines and
+/// lines starting with `#` are ignored. File patterns are appended after the
+/// CLI ones. As with CLI patterns, a file matching any exclude pattern is
+/// skipped even if it also matches an include pattern.
+pub fn read_patterns_file(
+    path: &Path,
+    include: &mut Vec<String>,
+    exclude: &mut Vec<String>,
+) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read patterns file {}", path.display()))?;
+
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(pattern) = line.strip_prefix('+') {
+            include.push(pattern.trim().to_string());
+        } else if let Some(pattern) = line.strip_prefix('-') {
+            exclude.push(pattern.trim().to_string());
+        } else {
+            anyhow::bail!(
+                "{}:{}: expected a line starting with '+' or '-', got '{line}'",
+                path.display(),
+                line_number + 1
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a `--concat-order` file: one glob per line, highest priority first.
+/// Blank lines and lines starting with `#` are ignored.
+pub fn read_order_file(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read order file {}", path.display()))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Parses an `--include-ext`/`--exclude-ext` value such as `rs` or `.rs`
+pub fn parse_extension(value: &str) -> Result<String, String> {
+    let ext = value.trim().trim_start_matches('.');
+    if ext.is_empty() || ext.contains(['/', '\\']) {
+        return Err(format!("invalid extension '{value}'"));
+    }
+    Ok(ext.to_string())
+}
+
+/// Parses a `--since` value such as `90m`, `12h`, `7d` or `2w`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}'"))?;
+
+    let seconds: u64 = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        other => {
+            return Err(format!(
+                "unknown duration unit '{other}' (expected s, m, h, d or w)"
+            ));
+        }
+    };
+
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{value}' is too large"))
+}
+
+/// Glob matching files with extension `ext` in any directory, ignoring case
+/// (`rs` becomes `*.[rR][sS]`). Other characters are bracketed so they are
+/// matched literally.
+pub fn extension_glob(ext: &str) -> String {
+    let mut glob = String::from("*.");
+    for c in ext.chars() {
+        let lower: String = c.to_lowercase().collect();
+        let upper: String = c.to_uppercase().collect();
+        if lower != upper {
+            let _ = write!(glob, "[{lower}{upper}]");
+        } else if c.is_alphanumeric() {
+            glob.push(c);
+        } else {
+            let _ = write!(glob, "[{c}]");
+        }
+    }
+    glob
+}
+
+/// Builds a matcher for `patterns` relative to the traversal root, or `None` if there are none
+fn build_matcher(options: &TraversalOptions, patterns: &[String]) -> Result<Option<Override>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = OverrideBuilder::new(&options.root);
+    // Must be set before any pattern is added
+    builder.case_insensitive(options.glob_case_insensitive)?;
+    for pattern in patterns {
+        builder.add(pattern)?;
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Files changed relative to `options.only_changed`, if set
+fn changed_set(options: &TraversalOptions) -> Result<Option<HashSet<PathBuf>>> {
+    options
+        .only_changed
+        .as_deref()
+        .map(|base| {
+            Ok(cloner::changed_files(&options.root, base)?
+                .into_iter()
+                .collect())
+        })
+        .transpose()
+}
+
+/// Matcher for `options.concat_order`, if set
+fn build_order(options: &TraversalOptions) -> Result<Option<GlobSet>> {
+    if options.concat_order.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in &options.concat_order {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .case_insensitive(options.glob_case_insensitive)
+            .build()
+            .with_context(|| format!("Invalid order pattern '{pattern}'"))?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Stably sorts files by the first order pattern matching their path
+/// relative to the root or their file name; unmatched files go last
+fn apply_order(files: &mut [PathBuf], root: &Path, order: &GlobSet) {
+    files.sort_by_cached_key(|path| {
+        let relative = format_path(path.strip_prefix(root).unwrap_or(path));
+        let name = path.file_name().unwrap_or_default();
+        order
+            .matches(&relative)
+            .into_iter()
+            .chain(order.matches(name))
+            .min()
+            .unwrap_or(usize::MAX)
+    });
+}
+
+/// Oldest modification time a file may have, if `options.since` is set
+fn since_cutoff(options: &TraversalOptions) -> Option<SystemTime> {
+    options.since.map(|since| {
+        SystemTime::now()
+            .checked_sub(since)
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    })
+}
+
+/// Whether a file was modified at or after `cutoff`. Files whose modification
+/// time cannot be read are kept rather than silently dropped.
+fn modified_since(path: &Path, cutoff: SystemTime) -> bool {
+    match std::fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified) => modified >= cutoff,
+        Err(err) => {
+            notice!(
+                "Could not read the modification time of {}, including it: {err}",
+                path.display()
+            );
+            true
+        }
+    }
+}
+
+/// Checks a path relative to the root against the exclude and include matchers
+fn is_selected(
+    relative_path: &Path,
+    exclude_matcher: Option<&Override>,
+    include_matcher: Option<&Override>,
+) -> bool {
+    // Exclude check
+    if let Some(matcher) = exclude_matcher {
+        let res = matcher.matched(relative_path, false);
+        // If matched (Whitelist), it means it matched an exclude pattern.
+        // So we should SKIP it.
+        if res.is_whitelist() {
+            log::debug!("Excluded file {} (pattern match)", relative_path.display());
+            return false;
+        }
+    }
+
+    // Include check
+    if let Some(matcher) = include_matcher {
+        let res = matcher.matched(relative_path, false);
+        // If matched (Whitelist), it means it matched an include pattern.
+        // If NOT matched (Ignore), we should SKIP it.
+        if !res.is_whitelist() {
+            log::debug!("Skipped file {} (not included)", relative_path.display());
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Reads a newline-separated list of files from `source` ("-" for stdin),
+/// in list order. Relative paths are resolved against `root`. Blank lines are
+/// ignored, and paths that are not existing files are skipped with a warning.
+pub fn read_file_list(source: &Path, root: &Path) -> Result<Vec<PathBuf>> {
+    let content = if source.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read file list from stdin")?
+    } else {
+        std::fs::read_to_string(source)
+            .with_context(|| format!("Failed to read file list {}", source.display()))?
+    };
+
+    let mut files = Vec::new();
+    for line in content.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let path = root.join(line);
+        if path.is_file() {
+            files.push(path);
+        } else {
+            notice!("Skipping {line} from the file list: not an existing file");
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `relative` is the directory given to `--exclude-dir`: by name when
+/// it has no `/`, otherwise by its path from the root
+fn is_excluded_dir(relative: &Path, dir: &str) -> bool {
+    let dir = dir.trim_matches('/');
+    if dir.contains('/') {
+        relative == Path::new(dir)
+    } else {
+        relative.file_name().is_some_and(|name| name == dir)
+    }
+}
+
+/// Smallest size a file must have to be collected, in bytes
+fn min_file_size(options: &TraversalOptions) -> u64 {
+    if options.skip_empty {
+        options.min_file_size.max(1)
+    } else {
+        options.min_file_size
+    }
+}
+
+/// Applies the include and exclude patterns and the size limits to files
+/// collected without a walk, matching paths relative to `options.root`
+pub fn filter_files(options: &TraversalOptions, files: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let exclude_matcher = build_matcher(options, &options.exclude)?;
+    let include_matcher = build_matcher(options, &options.include)?;
+    let changed = changed_set(options)?;
+    let cutoff = since_cutoff(options);
+    let order = build_order(options)?;
+    let min_file_size = min_file_size(options);
+
+    let mut files: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| {
+            min_file_size == 0 || std::fs::metadata(path).is_ok_and(|m| m.len() >= min_file_size)
+        })
+        .filter(|path| {
+            changed
+                .as_ref()
+                .is_none_or(|changed| changed.contains(path))
+        })
+        .filter(|path| cutoff.is_none_or(|cutoff| modified_since(path, cutoff)))
+        .filter(|path| {
+            let relative_path = path.strip_prefix(&options.root).unwrap_or(path);
+            // Listed files are not walked to, so check each of their directories
+            let pruned = relative_path.ancestors().skip(1).any(|dir| {
+                options
+                    .exclude_dirs
+                    .iter()
+                    .any(|excluded| is_excluded_dir(dir, excluded))
+            });
+            !pruned
+                && is_selected(
+                    relative_path,
+                    exclude_matcher.as_ref(),
+                    include_matcher.as_ref(),
+                )
+        })
+        .collect();
+    if let Some(order) = &order {
+        apply_order(&mut files, &options.root, order);
+    }
+    Ok(files)
 }
 
 pub fn traverse(options: &TraversalOptions) -> Result<Vec<PathBuf>> {
+    traverse_with(options, &|_| {})
+}
+
+/// Like [`traverse`], calling `on_file` for each matching file as soon as the
+/// walk finds it, before the list is sorted. With `max_files` it is only
+/// called for the files within the cap.
+pub fn traverse_with(
+    options: &TraversalOptions,
+    on_file: &(dyn Fn(&Path) + Sync),
+) -> Result<Vec<PathBuf>> {
     log::debug!("Traversing {}", options.root.display());
 
     // 1. Setup exclusions
     // MATCH BEHAVIOR: OverrideBuilder::add("pattern") creates a Whitelist rule.
     // So if a file matches "pattern", result is Whitelist.
     // If it doesn't match, result is Ignore (Unmatched).
-    let exclude_matcher = if options.exclude.is_empty() {
-        None
-    } else {
-        let mut builder = OverrideBuilder::new(&options.root);
-        for pattern in &options.exclude {
-            builder.add(pattern)?;
-        }
-        Some(builder.build()?)
-    };
+    let exclude_matcher = build_matcher(options, &options.exclude)?;
 
     let mut walker = WalkBuilder::new(&options.root);
-    walker.git_ignore(true); // We handle custom overrides manually below
+    // We handle custom overrides manually below. Files ignored here never
+    // reach the include/exclude checks, so `--include` cannot bring them back.
+    let git_ignore = !options.no_gitignore && !options.no_ignore;
+    walker.git_ignore(git_ignore);
+    walker.git_global(git_ignore);
+    walker.git_exclude(git_ignore);
+    // By default `.gitignore` only counts inside a repository (a `.git` directory)
+    walker.require_git(!options.apply_gitignore_always);
+    walker.ignore(options.ignore_files && !options.no_ignore);
+    walker.parents(!options.no_ignore);
+    if !options.no_ignore {
+        walker.add_custom_ignore_filename(IGNORE_FILENAME);
+    }
+    walker.hidden(!options.hidden);
+    // Pruned entries are never descended into, unlike files left out by `exclude`
+    let skip_git = options.hidden;
+    let root = options.root.clone();
+    let exclude_dirs = options.exclude_dirs.clone();
+    if skip_git || !exclude_dirs.is_empty() {
+        walker.filter_entry(move |entry| {
+            if skip_git && entry.file_name() == ".git" {
+                return false;
+            }
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            if is_dir && entry.depth() > 0 {
+                let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+                if exclude_dirs
+                    .iter()
+                    .any(|dir| is_excluded_dir(relative, dir))
+                {
+                    log::debug!("Pruned directory {}", entry.path().display());
+                    return false;
+                }
+            }
+            true
+        });
+    }
+    walker.follow_links(options.follow_symlinks);
+    walker.max_depth(options.max_depth);
 
     // 2. Setup inclusions
-    let include_matcher = if options.include.is_empty() {
-        None
-    } else {
-        let mut builder = OverrideBuilder::new(&options.root);
-        for pattern in &options.include {
-            builder.add(pattern)?;
-        }
-        Some(builder.build()?)
-    };
+    let include_matcher = build_matcher(options, &options.include)?;
+
+    let min_file_size = min_file_size(options);
+
+    let changed = changed_set(options)?;
+    let cutoff = since_cutoff(options);
+    let order = build_order(options)?;
 
-    let files: Vec<PathBuf> = walker
-        .build()
-        .par_bridge()
-        .filter_map(|result| {
-            match result {
-                Ok(entry) => {
-                    if !entry.file_type().is_some_and(|ft| ft.is_file()) {
-                        return None;
-                    }
-
-                    let path = entry.path();
-                    // OverrideBuilder expects relative paths from the root it was built with.
-                    let relative_path = path.strip_prefix(&options.root).unwrap_or(path);
-
-                    log::trace!(
-                        "Checking {} (rel: {})",
-                        path.display(),
-                        relative_path.display()
-                    );
-
-                    // Exclude check
-                    if let Some(matcher) = &exclude_matcher {
-                        let res = matcher.matched(relative_path, false);
-                        // If matched (Whitelist), it means it matched an exclude pattern.
-                        // So we should SKIP it.
-                        if res.is_whitelist() {
-                            log::debug!(
-                                "Excluded file {} (pattern match)",
-                                relative_path.display()
-                            );
-                            return None;
-                        }
-                    }
-
-                    // Include check
-                    if let Some(matcher) = &include_matcher {
-                        let res = matcher.matched(relative_path, false);
-                        // If matched (Whitelist), it means it matched an include pattern.
-                        // If NOT matched (Ignore), we should SKIP it.
-                        if !res.is_whitelist() {
-                            log::debug!("Skipped file {} (not included)", relative_path.display());
-                            return None;
-                        }
-                    }
-
-                    Some(path.to_path_buf())
+    let walk_errors = Mutex::new(Vec::new());
+    let keep = |result: Result<DirEntry, ignore::Error>| -> Option<PathBuf> {
+        match result {
+            Ok(entry) => {
+                if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+                    return None;
                 }
-                Err(err) => {
-                    log::error!("Traversal error: {err}");
-                    None
+
+                // Filtered here rather than in ingest so tiny files stay
+                // out of the prologue too
+                if min_file_size > 0 && entry.metadata().is_ok_and(|m| m.len() < min_file_size) {
+                    log::debug!("Skipped small file {}", entry.path().display());
+                    return None;
+                }
+
+                if let Some(changed) = &changed
+                    && !changed.contains(entry.path())
+                {
+                    log::trace!("Skipped unchanged file {}", entry.path().display());
+                    return None;
                 }
+
+                if let Some(cutoff) = cutoff
+                    && !modified_since(entry.path(), cutoff)
+                {
+                    log::trace!("Skipped old file {}", entry.path().display());
+                    return None;
+                }
+
+                let path = entry.path();
+                // OverrideBuilder expects relative paths from the root it was built with.
+                let relative_path = path.strip_prefix(&options.root).unwrap_or(path);
+
+                log::trace!(
+                    "Checking {} (rel: {})",
+                    path.display(),
+                    relative_path.display()
+                );
+
+                if !is_selected(
+                    relative_path,
+                    exclude_matcher.as_ref(),
+                    include_matcher.as_ref(),
+                ) {
+                    return None;
+                }
+
+                Some(path.to_path_buf())
             }
-        })
-        .collect();
+            Err(err) => {
+                log::error!("Traversal error: {err}");
+                if options.strict {
+                    walk_errors
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(err);
+                }
+                None
+            }
+        }
+    };
+    let mut files: Vec<PathBuf> = if let Some(max) = options.max_files {
+        // Walked in order, one thread, so the same files make the cut every run
+        walker.sort_by_file_name(|a, b| a.cmp(b));
+        let mut files = Vec::new();
+        let mut walk = walker.build().filter_map(keep);
+        for path in walk.by_ref().take(max) {
+            on_file(&path);
+            files.push(path);
+        }
+        // One more match tells us whether the limit actually cut anything
+        if walk.next().is_some() {
+            notice!(
+                "Stopped after {max} files in {} (--max-files); the rest were not collected",
+                options.root.display()
+            );
+        }
+        files
+    } else {
+        walker
+            .build()
+            .par_bridge()
+            .filter_map(keep)
+            .inspect(|path| on_file(path))
+            .collect()
+    };
+
+    let walk_errors = walk_errors
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(first) = walk_errors.first() {
+        anyhow::bail!(
+            "Traversal of {} failed with {} error(s), first: {first}",
+            options.root.display(),
+            walk_errors.len()
+        );
+    }
+
+    // The parallel walk yields entries in no particular order; sort by the
+    // forward-slash relative path so the digest is identical on every platform
+    files.sort_by_cached_key(|path| format_path(path.strip_prefix(&options.root).unwrap_or(path)));
+
+    // Stable sorts keep path order among files with equal size or mtime
+    match options.sort {
+        SortOrder::Path => {}
+        SortOrder::Size => files.sort_by_cached_key(|path| {
+            std::cmp::Reverse(std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        }),
+        SortOrder::Mtime => files.sort_by_cached_key(|path| {
+            std::cmp::Reverse(std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        }),
+    }
 
-    let mut files = files;
-    files.sort();
+    if options.reverse {
+        files.reverse();
+    }
+
+    if let Some(order) = &order {
+        apply_order(&mut files, &options.root, order);
+    }
+
+    if options.follow_symlinks {
+        // The same real file can be reached through several links; keep the first path
+        let mut seen = HashSet::new();
+        files.retain(|path| {
+            let real = path.canonicalize().unwrap_or_else(|_| path.clone());
+            let first = seen.insert(real);
+            if !first {
+                log::debug!("Skipped duplicate {} (already visited)", path.display());
+            }
+            first
+        });
+    }
 
     Ok(files)
 }
@@ -121,6 +613,7 @@ mod tests {
             root: root.to_path_buf(),
             include: vec!["*.rs".to_string()],
             exclude: vec![],
+            ..TraversalOptions::default()
         };
 
         let files = traverse(&options)?;
@@ -142,6 +635,7 @@ mod tests {
             root: root.to_path_buf(),
             include: vec!["*.rs".to_string()],
             exclude: vec!["bad.rs".to_string()],
+            ..TraversalOptions::default()
         };
 
         let files = traverse(&options)?;
@@ -165,6 +659,7 @@ mod tests {
             root: root.to_path_buf(),
             include: vec![],
             exclude: vec!["**/*.lock".to_string()],
+            ..TraversalOptions::default()
         };
 
         let files = traverse(&options)?;
@@ -181,4 +676,614 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_traverse_follow_symlinks() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        fs::create_dir(root.join("shared"))?;
+        File::create(root.join("shared/lib.rs"))?;
+        fs::create_dir(root.join("app"))?;
+        std::os::unix::fs::symlink(root.join("shared"), root.join("app/linked"))?;
+        // A link back to the root would loop forever without cycle detection
+        std::os::unix::fs::symlink(root, root.join("app/loop"))?;
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            ..TraversalOptions::default()
+        };
+        let files = traverse(&options)?;
+        assert_eq!(files.len(), 1);
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            follow_symlinks: true,
+            ..TraversalOptions::default()
+        };
+        let files = traverse(&options)?;
+        assert_eq!(files.len(), 1, "Files found: {files:?}");
+        assert!(files[0].ends_with("app/linked/lib.rs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_max_depth() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        fs::create_dir_all(root.join("src/nested"))?;
+        File::create(root.join("top.rs"))?;
+        File::create(root.join("top.txt"))?;
+        File::create(root.join("src/lib.rs"))?;
+        File::create(root.join("src/nested/deep.rs"))?;
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            include: vec!["*.rs".to_string()],
+            max_depth: Some(1),
+            ..TraversalOptions::default()
+        };
+        let files = traverse(&options)?;
+        assert_eq!(files.len(), 1, "Files found: {files:?}");
+        assert!(files[0].ends_with("top.rs"));
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            include: vec!["*.rs".to_string()],
+            max_depth: Some(2),
+            ..TraversalOptions::default()
+        };
+        let files = traverse(&options)?;
+        assert_eq!(files.len(), 2, "Files found: {files:?}");
+        assert!(files.iter().any(|p| p.ends_with("src/lib.rs")));
+        assert!(!files.iter().any(|p| p.ends_with("deep.rs")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_gitmeltignore() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        fs::create_dir_all(root.join("docs/generated"))?;
+        File::create(root.join("main.rs"))?;
+        File::create(root.join("CHANGELOG.md"))?;
+        File::create(root.join("docs/guide.md"))?;
+        File::create(root.join("docs/generated/api.md"))?;
+        fs::write(root.join(IGNORE_FILENAME), "CHANGELOG.md\n")?;
+        fs::write(root.join("docs").join(IGNORE_FILENAME), "generated/\n")?;
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            include: vec!["*.md".to_string(), "*.rs".to_string()],
+            ..TraversalOptions::default()
+        };
+
+        let files = traverse(&options)?;
+        assert_eq!(files.len(), 2, "Files found: {files:?}");
+        assert!(files.iter().any(|p| p.ends_with("main.rs")));
+        assert!(files.iter().any(|p| p.ends_with("docs/guide.md")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_ignore_flags() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        fs::create_dir(root.join(".git"))?;
+        File::create(root.join("main.rs"))?;
+        File::create(root.join("git_ignored.rs"))?;
+        File::create(root.join("dot_ignored.rs"))?;
+        File::create(root.join("melt_ignored.rs"))?;
+        fs::write(root.join(".gitignore"), "git_ignored.rs\n")?;
+        fs::write(root.join(".ignore"), "dot_ignored.rs\n")?;
+        fs::write(root.join(IGNORE_FILENAME), "melt_ignored.rs\n")?;
+
+        let found = |options: TraversalOptions| -> Result<Vec<String>> {
+            let mut names: Vec<String> = traverse(&TraversalOptions {
+                root: root.to_path_buf(),
+                include: vec!["*.rs".to_string()],
+                ..options
+            })?
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        assert_eq!(
+            found(TraversalOptions::default())?,
+            ["dot_ignored.rs", "main.rs"]
+        );
+        assert_eq!(
+            found(TraversalOptions {
+                ignore_files: true,
+                ..TraversalOptions::default()
+            })?,
+            ["main.rs"]
+        );
+        assert_eq!(
+            found(TraversalOptions {
+                no_gitignore: true,
+                ..TraversalOptions::default()
+            })?,
+            ["dot_ignored.rs", "git_ignored.rs", "main.rs"]
+        );
+        assert_eq!(
+            found(TraversalOptions {
+                no_ignore: true,
+                ignore_files: true,
+                ..TraversalOptions::default()
+            })?,
+            [
+                "dot_ignored.rs",
+                "git_ignored.rs",
+                "main.rs",
+                "melt_ignored.rs"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_gitignore_without_git() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "ignored.txt\n")?;
+        File::create(root.join("ignored.txt"))?;
+        File::create(root.join("kept.txt"))?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        assert_eq!(
+            traverse(&options)?,
+            vec![root.join("ignored.txt"), root.join("kept.txt")]
+        );
+
+        options.apply_gitignore_always = true;
+        assert_eq!(traverse(&options)?, vec![root.join("kept.txt")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_hidden() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        fs::create_dir_all(root.join(".git"))?;
+        fs::create_dir_all(root.join(".github/workflows"))?;
+        File::create(root.join(".git/config"))?;
+        File::create(root.join(".github/workflows/ci.yml"))?;
+        File::create(root.join(".env.example"))?;
+        File::create(root.join(".env"))?;
+        File::create(root.join("main.rs"))?;
+        fs::write(root.join(".gitignore"), ".env\n")?;
+
+        let names = |options: &TraversalOptions| -> Result<Vec<String>> {
+            let mut names: Vec<String> = traverse(options)?
+                .iter()
+                .map(|p| format_path(p.strip_prefix(root).unwrap()))
+                .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            ..TraversalOptions::default()
+        };
+        assert_eq!(names(&options)?, ["main.rs"]);
+
+        options.hidden = true;
+        assert_eq!(
+            names(&options)?,
+            [
+                ".env.example",
+                ".github/workflows/ci.yml",
+                ".gitignore",
+                "main.rs"
+            ]
+        );
+
+        options.include = vec![".github/**".to_string()];
+        assert_eq!(names(&options)?, [".github/workflows/ci.yml"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_glob_case_insensitive() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        File::create(root.join("main.rs"))?;
+        File::create(root.join("README.md"))?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            include: vec!["*.RS".to_string()],
+            ..TraversalOptions::default()
+        };
+        assert!(traverse(&options)?.is_empty());
+
+        options.glob_case_insensitive = true;
+        let files = traverse(&options)?;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with("main.rs"));
+
+        options.include.clear();
+        options.exclude = vec!["readme.MD".to_string()];
+        let files = traverse(&options)?;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with("main.rs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_min_file_size() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        File::create(root.join("__init__.py"))?;
+        fs::write(root.join("main.py"), "print()")?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        assert_eq!(traverse(&options)?.len(), 2);
+
+        options.min_file_size = 1;
+        assert_eq!(traverse(&options)?, vec![root.join("main.py")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_skip_empty() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        File::create(root.join("__init__.py"))?;
+        fs::write(root.join("main.py"), "print()")?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        assert_eq!(traverse(&options)?.len(), 2);
+
+        options.skip_empty = true;
+        assert_eq!(traverse(&options)?, vec![root.join("main.py")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_since() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::write(root.join("new.rs"), "fn new() {}")?;
+        let old = File::create(root.join("old.rs"))?;
+        old.set_modified(SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60))?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            since: Some(parse_duration("7d").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(traverse(&options)?, vec![root.join("new.rs")]);
+
+        options.since = Some(parse_duration("2w").unwrap());
+        assert_eq!(traverse(&options)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 86_400)));
+        assert_eq!(parse_duration("1w"), Ok(Duration::from_secs(604_800)));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+    }
+
+    #[test]
+    fn test_traverse_max_files() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        for i in 0..10 {
+            File::create(root.join(format!("file{i}.txt")))?;
+        }
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            max_files: Some(3),
+            ..TraversalOptions::default()
+        };
+        // The first files by name, every time
+        let expected: Vec<PathBuf> = (0..3).map(|i| root.join(format!("file{i}.txt"))).collect();
+        for _ in 0..5 {
+            assert_eq!(traverse(&options)?, expected);
+        }
+        // Files past the cap are never handed out early
+        let seen = Mutex::new(Vec::new());
+        traverse_with(&options, &|path| {
+            seen.lock().unwrap().push(path.to_path_buf());
+        })?;
+        assert_eq!(seen.into_inner().unwrap(), expected);
+
+        options.max_files = Some(10);
+        assert_eq!(traverse(&options)?.len(), 10);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_traverse_strict() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::write(root.join("main.rs"), "fn main() {}")?;
+        std::os::unix::fs::symlink(root.join("gone"), root.join("broken"))?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        assert_eq!(traverse(&options)?, vec![root.join("main.rs")]);
+
+        options.strict = true;
+        let error = traverse(&options).unwrap_err();
+        assert!(error.to_string().contains("1 error(s)"), "{error}");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exclude_dir_is_never_entered() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::create_dir_all(root.join("node_modules/pkg"))?;
+        fs::create_dir_all(root.join("vendor/big"))?;
+        fs::create_dir_all(root.join("src/vendor/big"))?;
+        fs::write(root.join("main.rs"), "fn main() {}")?;
+        fs::write(root.join("node_modules/pkg/index.js"), "")?;
+        fs::write(root.join("vendor/big/lib.c"), "")?;
+        fs::write(root.join("src/vendor/big/lib.c"), "")?;
+        // A strict walk fails on the broken link if it looks inside node_modules
+        std::os::unix::fs::symlink(root.join("gone"), root.join("node_modules/pkg/broken"))?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            follow_symlinks: true,
+            strict: true,
+            exclude: vec!["**/node_modules/**".to_string()],
+            ..Default::default()
+        };
+        assert!(traverse(&options).is_err());
+
+        options.exclude.clear();
+        options.exclude_dirs = vec!["node_modules".to_string(), "vendor/big/".to_string()];
+        // A path only matches from the root, a name at any depth
+        assert_eq!(
+            traverse(&options)?,
+            vec![root.join("main.rs"), root.join("src/vendor/big/lib.c")]
+        );
+
+        // Listed files are held to the same rule
+        let listed = filter_files(
+            &options,
+            vec![root.join("node_modules/pkg/index.js"), root.join("main.rs")],
+        )?;
+        assert_eq!(listed, vec![root.join("main.rs")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_file_list() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::create_dir(root.join("src"))?;
+        fs::write(root.join("src/main.rs"), "fn main() {}")?;
+        fs::write(root.join("README.md"), "# readme")?;
+        let list = root.join("files.txt");
+        fs::write(&list, "src/main.rs\r\n\nmissing.rs\nsrc\nREADME.md\n")?;
+
+        // List order is kept; missing files and directories are skipped
+        let files = read_file_list(&list, root)?;
+        assert_eq!(
+            files,
+            vec![root.join("src/main.rs"), root.join("README.md")]
+        );
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            include: vec!["*.rs".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            filter_files(&options, files.clone())?,
+            vec![root.join("src/main.rs")]
+        );
+
+        // So do the size limits
+        fs::write(root.join("README.md"), "")?;
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            skip_empty: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            filter_files(&options, files)?,
+            vec![root.join("src/main.rs")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extension_glob() -> Result<()> {
+        assert_eq!(parse_extension(".rs").as_deref(), Ok("rs"));
+        assert!(parse_extension(".").is_err());
+        assert_eq!(extension_glob("rs"), "*.[rR][sS]");
+        assert_eq!(extension_glob("mp3"), "*.[mM][pP]3");
+        assert_eq!(extension_glob("c++"), "*.[cC][+][+]");
+
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::create_dir(root.join("src"))?;
+        File::create(root.join("src/main.rs"))?;
+        File::create(root.join("README.MD"))?;
+        File::create(root.join("Cargo.lock"))?;
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            include: vec![extension_glob("rs"), extension_glob("md")],
+            ..Default::default()
+        };
+        assert_eq!(
+            traverse(&options)?,
+            vec![root.join("README.MD"), root.join("src/main.rs")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_patterns_file() -> Result<()> {
+        let dir = tempdir()?;
+        let patterns = dir.path().join("patterns.txt");
+        fs::write(&patterns, "# sources\n+*.rs\n\n-target/**\n  + *.toml \n")?;
+
+        let mut include = vec!["*.md".to_string()];
+        let mut exclude = vec![];
+        read_patterns_file(&patterns, &mut include, &mut exclude)?;
+
+        assert_eq!(include, vec!["*.md", "*.rs", "*.toml"]);
+        assert_eq!(exclude, vec!["target/**"]);
+
+        fs::write(&patterns, "*.rs\n")?;
+        assert!(read_patterns_file(&patterns, &mut include, &mut exclude).is_err());
+        assert!(
+            read_patterns_file(&dir.path().join("missing"), &mut include, &mut exclude).is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_concat_order() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::create_dir(root.join("src"))?;
+        for file in [
+            "AAA.txt",
+            "README.md",
+            "src/lib.rs",
+            "src/main.rs",
+            "build.rs",
+        ] {
+            File::create(root.join(file))?;
+        }
+        let order_file = dir.path().join("order.txt");
+        fs::write(&order_file, "# docs first\nREADME.md\n\nsrc/lib.rs\n*.rs\n")?;
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            exclude: vec!["order.txt".to_string()],
+            concat_order: read_order_file(&order_file)?,
+            ..Default::default()
+        };
+        let files = traverse(&options)?;
+        let names: Vec<String> = files
+            .iter()
+            .map(|path| format_path(path.strip_prefix(root).unwrap()))
+            .collect();
+
+        assert_eq!(
+            names,
+            [
+                "README.md",
+                "src/lib.rs",
+                "build.rs",
+                "src/main.rs",
+                "AAA.txt"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_sorted_order() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        fs::create_dir_all(root.join("b/c"))?;
+        fs::create_dir_all(root.join("a"))?;
+        for file in ["z.txt", "b/c/d.txt", "b/a.txt", "a/z.txt", "a.txt", "B.txt"] {
+            File::create(root.join(file))?;
+        }
+
+        let options = TraversalOptions {
+            root: root.to_path_buf(),
+            ..TraversalOptions::default()
+        };
+
+        let files: Vec<String> = traverse(&options)?
+            .iter()
+            .map(|p| format_path(p.strip_prefix(root).unwrap()))
+            .collect();
+        assert_eq!(
+            files,
+            vec!["B.txt", "a.txt", "a/z.txt", "b/a.txt", "b/c/d.txt", "z.txt"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traverse_sort_by_size() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+
+        fs::write(root.join("small.txt"), "a")?;
+        fs::write(root.join("large.txt"), "aaaaaaaaaa")?;
+        fs::write(root.join("medium.txt"), "aaaaa")?;
+
+        let mut options = TraversalOptions {
+            root: root.to_path_buf(),
+            sort: SortOrder::Size,
+            ..TraversalOptions::default()
+        };
+
+        let files = traverse(&options)?;
+        assert!(files[0].ends_with("large.txt"));
+        assert!(files[1].ends_with("medium.txt"));
+        assert!(files[2].ends_with("small.txt"));
+
+        options.reverse = true;
+        let files = traverse(&options)?;
+        assert!(files[0].ends_with("small.txt"));
+        assert!(files[2].ends_with("large.txt"));
+
+        Ok(())
+    }
 }
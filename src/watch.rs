@@ -0,0 +1,151 @@
+use anyhow::Result;
+use gitmelt::ingest::{chunk_path, output_file_path};
+use log::{error, info};
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+/// Quiet period after the last change before the digest is regenerated
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Size and modification time of each file, used to tell real changes from
+/// events caused by merely reading the files
+pub type Snapshot = HashMap<PathBuf, (u64, Option<SystemTime>)>;
+
+/// Watches `roots` recursively and calls `rebuild` after each burst of
+/// changes. Runs until the process is interrupted.
+pub fn watch(roots: &[PathBuf], mut rebuild: impl FnMut() -> Result<()>) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, tx)?;
+    for root in roots {
+        debouncer.watcher().watch(root, RecursiveMode::Recursive)?;
+    }
+    info!("Watching for changes, press Ctrl-C to stop");
+
+    for result in rx {
+        match result {
+            // A failed rebuild should not end the watch
+            Ok(_) => {
+                if let Err(e) = rebuild() {
+                    error!("Failed to regenerate digest: {e:#}");
+                }
+            }
+            Err(e) => error!("Watch error: {e}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Takes a snapshot of `files`, leaving out the digest's own output files so
+/// writing the digest does not trigger another rebuild. `sidecars` are other
+/// files written on every rebuild, such as the manifest.
+pub fn snapshot(files: &[PathBuf], output: &Path, sidecars: &[PathBuf]) -> Snapshot {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let output = canonical(output);
+    let sidecars: Vec<PathBuf> = sidecars.iter().map(|path| canonical(path)).collect();
+    files
+        .iter()
+        .filter_map(|file| {
+            let path = file.canonicalize().ok()?;
+            if is_output_file(&path, &output) || sidecars.contains(&path) {
+                return None;
+            }
+            let metadata = std::fs::metadata(&path).ok()?;
+            Some((path, (metadata.len(), metadata.modified().ok())))
+        })
+        .collect()
+}
+
+/// Matches the output file and its `.gz` and numbered chunk variants
+fn is_output_file(path: &Path, output: &Path) -> bool {
+    let is_output =
+        |candidate: PathBuf| path == candidate || path == output_file_path(&candidate, true);
+    if is_output(output.to_path_buf()) {
+        return true;
+    }
+    // Chunk numbers are the all-digit parts of the name
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    name.split('.')
+        .filter_map(|part| part.parse::<usize>().ok())
+        .any(|chunk| is_output(chunk_path(output, chunk)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_snapshot_detects_changes() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        let main = root.join("main.rs");
+        let output = root.join("digest.txt");
+        std::fs::write(&main, "fn main() {}")?;
+        std::fs::write(&output, "")?;
+        std::fs::write(root.join("digest.002.txt"), "")?;
+
+        let manifest = root.join("manifest.tsv");
+        std::fs::write(&manifest, "")?;
+
+        let files = vec![
+            main.clone(),
+            output.clone(),
+            root.join("digest.002.txt"),
+            manifest.clone(),
+        ];
+        let sidecars = [manifest.clone()];
+        let before = snapshot(&files, &output, &sidecars);
+        assert_eq!(before.len(), 1);
+
+        // Reading or rewriting the digest is not a change
+        std::fs::read(&main)?;
+        std::fs::write(&output, "digest")?;
+        std::fs::write(&manifest, "0\t6\tmain.rs")?;
+        assert_eq!(snapshot(&files, &output, &sidecars), before);
+
+        std::fs::write(&main, "fn main() { println!(); }")?;
+        assert_ne!(snapshot(&files, &output, &sidecars), before);
+
+        std::fs::remove_file(&main)?;
+        assert!(snapshot(&files, &output, &sidecars).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_output_file() {
+        let output = Path::new("out/digest.txt");
+        for name in [
+            "digest.txt",
+            "digest.txt.gz",
+            "digest.002.txt",
+            "digest.002.txt.gz",
+            "digest.1234.txt",
+        ] {
+            assert!(
+                is_output_file(&Path::new("out").join(name), output),
+                "{name}"
+            );
+        }
+        for name in [
+            "digest.txt.bak",
+            "digest-notes.md",
+            "digest.rs",
+            "digest.old.txt",
+            "other.002.txt",
+        ] {
+            assert!(
+                !is_output_file(&Path::new("out").join(name), output),
+                "{name}"
+            );
+        }
+        assert!(!is_output_file(Path::new("src/digest.txt"), output));
+    }
+}
@@ -48,7 +48,7 @@ fn test_gitignore_logic() -> Result<(), Box<dyn std::error::Error>> {
     writeln!(gitignore, "secret.txt")?;
 
     // Create files
-    File::create(root.join("secret.txt"))?;
+    fs::write(root.join("secret.txt"), "secret")?;
     let mut public = File::create(root.join("public.txt"))?;
     writeln!(public, "Public info")?;
 
@@ -129,3 +129,574 @@ fn test_include_exclude_complexity() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_json_preset_is_valid_json() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+
+    let mut a = File::create(root.join("a.txt"))?;
+    writeln!(a, "line with \"quotes\" and \\ backslash")?;
+
+    let mut b = File::create(root.join("b.txt"))?;
+    writeln!(b, "second file")?;
+
+    File::create(root.join("c.bin"))?.write_all(&[0u8; 100])?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.arg(root.to_str().unwrap())
+        .arg("--preset")
+        .arg("json")
+        .arg("--output")
+        .arg(root.join("digest.json"));
+
+    cmd.assert().success();
+
+    let output = fs::read_to_string(root.join("digest.json"))?;
+    let value: serde_json::Value = serde_json::from_str(&output)?;
+
+    let files = value["files"].as_array().unwrap();
+    assert_eq!(files.len(), 3);
+    assert!(files[0]["path"].as_str().unwrap().ends_with("a.txt"));
+    assert_eq!(
+        files[0]["content"],
+        "line with \"quotes\" and \\ backslash\n"
+    );
+    assert!(files[0]["tokens"].as_u64().unwrap() > 0);
+    assert_eq!(files[2]["skipped"], "Skipped: Binary");
+    assert!(value["total_tokens"].as_u64().unwrap() > 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_show_tokens() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+
+    let mut f = File::create(root.join("main.rs"))?;
+    writeln!(f, "fn main() {{}}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.arg(root.to_str().unwrap())
+        .arg("--stdout")
+        .arg("--show-tokens");
+
+    cmd.assert().success().stdout(predicate::str::is_match(
+        r"FILE: .*main\.rs \(\d+ tokens\)",
+    )?);
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.arg(root.to_str().unwrap())
+        .arg("--stdout")
+        .arg("--show-tokens")
+        .arg("--no-tokens");
+
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_config_file() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+
+    let mut config = File::create(root.join(".gitmelt.toml"))?;
+    writeln!(config, "preset = \"markdown\"")?;
+    writeln!(config, "exclude = [\"skip.rs\"]")?;
+
+    let mut main_rs = File::create(root.join("main.rs"))?;
+    writeln!(main_rs, "fn main() {{}}")?;
+    fs::write(root.join("skip.rs"), "fn skip() {}")?;
+
+    // Config is discovered from the working directory
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root).arg("--stdout").arg("--no-tokens");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("## File: main.rs"))
+        .stdout(predicate::str::contains("skip.rs").not());
+
+    // CLI flags override config values
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .arg("--stdout")
+        .arg("--no-tokens")
+        .arg("--preset")
+        .arg("xml");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<file path=\"main.rs\">"));
+
+    // --no-config bypasses discovery
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .arg("--stdout")
+        .arg("--no-tokens")
+        .arg("--no-config");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("FILE: skip.rs"));
+
+    Ok(())
+}
+
+#[test]
+fn test_multiple_inputs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+
+    for name in ["crate-b", "crate-a"] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        let mut lib = File::create(root.join(name).join("src/lib.rs"))?;
+        writeln!(lib, "// {name}")?;
+    }
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .arg("crate-b")
+        .arg("crate-a")
+        .arg("--stdout")
+        .arg("--no-tokens");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output_str = String::from_utf8(output)?;
+
+    assert!(output_str.contains("- crate-b/src/lib.rs"));
+    assert!(output_str.contains("- crate-a/src/lib.rs"));
+
+    // Inputs are concatenated in argument order
+    let pos_b = output_str.find("// crate-b").unwrap();
+    let pos_a = output_str.find("// crate-a").unwrap();
+    assert!(pos_b < pos_a, "crate-b should come before crate-a");
+
+    Ok(())
+}
+
+#[test]
+fn test_html_preset() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+
+    let mut f = File::create(root.join("main.rs"))?;
+    writeln!(f, "fn lt(a: i32, b: i32) -> bool {{ a < b && b > 0 }}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.arg(root.to_str().unwrap())
+        .arg("--stdout")
+        .arg("--no-tokens")
+        .arg("--preset")
+        .arg("html");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("<!DOCTYPE html>"))
+        .stdout(predicate::str::contains("a &lt; b &amp;&amp; b &gt; 0"))
+        .stdout(predicate::str::contains("<details>"))
+        .stdout(predicate::str::ends_with("</html>\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_metrics_json() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+
+    fs::write(root.join("main.rs"), "fn main() {}\n")?;
+    fs::write(root.join("image.bin"), [0u8, 159, 146, 150, 0, 1, 2])?;
+    fs::write(root.join("big.txt"), "x".repeat(2048))?;
+    let metrics_path = temp.path().join("metrics.json");
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.arg(root.to_str().unwrap())
+        .arg("--dry")
+        .arg("--max-file-size")
+        .arg("1KB")
+        .arg("--exclude")
+        .arg("metrics.json")
+        .arg("--metrics-json")
+        .arg(&metrics_path);
+    cmd.assert().success();
+
+    let metrics: serde_json::Value = serde_json::from_str(&fs::read_to_string(metrics_path)?)?;
+    assert_eq!(metrics["file_count"], 1);
+    assert_eq!(metrics["total_bytes"], 13);
+    assert_eq!(metrics["skipped_binary"], 1);
+    assert_eq!(metrics["skipped_large"], 1);
+    assert!(metrics["total_tokens"].as_u64().unwrap() > 0);
+    assert!(metrics["durations_secs"]["total"].is_f64());
+
+    Ok(())
+}
+
+#[test]
+fn test_files_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::write(root.join("listed.txt"), "Listed content")?;
+    fs::write(root.join("unlisted.txt"), "Unlisted content")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .arg("--files-from")
+        .arg("-")
+        .arg("--stdout")
+        .arg("--no-tokens")
+        .write_stdin("listed.txt\nmissing.txt\n");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Listed content"))
+        .stdout(predicate::str::contains("Unlisted content").not())
+        .stderr(predicate::str::contains("missing.txt"));
+
+    Ok(())
+}
+
+#[test]
+fn test_token_summary_stays_off_stdout() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    fs::write(temp.path().join("main.rs"), "fn main() {}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path()).arg("--stdout");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Total estimated tokens").not())
+        .stderr(predicate::str::contains("Total estimated tokens"));
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path()).arg("--stdout").arg("--quiet");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("fn main() {}"))
+        .stderr(predicate::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_count_only_breakdown() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::create_dir_all(root.join("vendor/dep"))?;
+    fs::write(
+        root.join("vendor/dep/lib.rs"),
+        "pub fn dep() {}\n".repeat(50),
+    )?;
+    fs::write(root.join("main.rs"), "fn main() {}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root).arg("--count-only");
+
+    cmd.assert().success().stdout(predicate::str::is_match(
+        r"Tokens by directory:\n  vendor/ +[\d,]+ +\d+\.\d%\n  \./ ",
+    )?);
+    assert!(!root.join("digest.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_split_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::create_dir_all(root.join("src"))?;
+    fs::write(root.join("src/main.rs"), "fn main() {}")?;
+    fs::write(root.join("Cargo.toml"), "[package]")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["src", "Cargo.toml", "--split-dir", "out"]);
+    cmd.assert().success();
+
+    let entry = fs::read_to_string(root.join("out/src/main.rs.txt"))?;
+    assert!(entry.contains("FILE: src/main.rs") && entry.contains("fn main() {}"));
+    assert!(root.join("out/Cargo.toml.txt").is_file());
+    assert!(fs::read_to_string(root.join("out/index.txt"))?.contains("src/main.rs"));
+    assert!(!root.join("digest.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_output_dash_is_stdout() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::write(root.join("main.rs"), "fn main() {}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root).args(["--output", "-", "--no-tokens"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("FILE: main.rs"))
+        .stdout(predicate::str::contains("fn main() {}"));
+    assert!(!root.join("-").exists());
+    assert!(!root.join("digest.txt").exists());
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["-o", "-", "--split-tokens", "100"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--split-tokens cannot be used"));
+
+    Ok(())
+}
+
+#[test]
+fn test_output_name() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let project = temp.path().join("project");
+    fs::create_dir(&project)?;
+    fs::write(project.join("main.rs"), "fn main() {}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path())
+        .args(["project", "--output-name", "{repo}.txt", "--no-tokens"]);
+    cmd.assert().success();
+
+    let digest = fs::read_to_string(temp.path().join("project.txt"))?;
+    assert!(digest.contains("fn main() {}"));
+    assert!(!temp.path().join("digest.txt").exists());
+
+    // `.` is named after the directory it resolves to
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(&project)
+        .args(["--output-name", "{repo}-digest.txt", "--no-tokens"]);
+    cmd.assert().success();
+    assert!(project.join("project-digest.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_format_detect() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::write(root.join("main.rs"), "fn main() {}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["-o", "repo.md", "--format-detect", "--no-tokens"]);
+    cmd.assert().success();
+    let digest = fs::read_to_string(root.join("repo.md"))?;
+    assert!(digest.contains("```rs\nfn main() {}"), "{digest}");
+
+    // An explicit preset wins over the extension
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root).args([
+        "-o",
+        "repo.json",
+        "--format-detect",
+        "--preset",
+        "xml",
+        "--no-tokens",
+    ]);
+    cmd.assert().success();
+    let digest = fs::read_to_string(root.join("repo.json"))?;
+    assert!(digest.contains("<file path=\"main.rs\">"), "{digest}");
+
+    Ok(())
+}
+
+#[test]
+fn test_tree_to_stderr() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::create_dir(root.join("src"))?;
+    fs::write(root.join("src/main.rs"), "fn main() {}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["--stdout", "--tree-to-stderr", "--no-tokens"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("fn main() {}"))
+        .stdout(predicate::str::contains("File structure").not())
+        .stdout(predicate::str::contains("Files included").not())
+        .stderr(predicate::str::contains(
+            "File structure:\n└── src/\n    └── main.rs\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_git_meta_only_for_repositories() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let (repo, plain) = (temp.path().join("repo"), temp.path().join("plain"));
+    fs::create_dir_all(&repo)?;
+    fs::create_dir_all(&plain)?;
+    fs::write(repo.join("tracked.txt"), "tracked")?;
+    fs::write(plain.join("loose.txt"), "loose")?;
+    for args in [
+        &["init", "-q"][..],
+        &["add", "."],
+        &["commit", "-q", "-m", "init"],
+    ] {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()?;
+        assert!(status.success());
+    }
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path())
+        .args(["repo", "plain", "--stdout", "--git-meta", "--no-tokens"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "FILE: repo/tracked.txt (last commit ",
+        ))
+        .stdout(predicate::str::contains("FILE: plain/loose.txt\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_append_rejects_wrapping_presets() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    fs::write(temp.path().join("a.txt"), "a")?;
+
+    for preset in ["xml", "json", "html"] {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+        cmd.current_dir(temp.path())
+            .args(["--append", "--preset", preset, "-o", "digest.out"]);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("--append only works"));
+    }
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path())
+        .args(["--append", "--preset", "markdown", "-o", "digest.out"]);
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn test_group_depth_must_be_positive() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    fs::write(temp.path().join("a.txt"), "a")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path())
+        .args(["--stdout", "--group-by-dir", "--group-depth", "0"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--group-depth"));
+
+    Ok(())
+}
+
+#[test]
+fn test_threads_must_be_positive() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    fs::write(temp.path().join("a.txt"), "a")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path())
+        .args(["--stdout", "--threads", "0"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--threads"));
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path())
+        .args(["--stdout", "--threads", "1"]);
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn test_interactive_requires_terminal() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::write(root.join("a.txt"), "a")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["--stdout", "--interactive", "--no-tokens"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--interactive needs a terminal"));
+
+    Ok(())
+}
+
+#[test]
+fn test_show_lines_without_tokens() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    fs::write(temp.path().join("main.rs"), "fn main() {\n}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(temp.path())
+        .args(["--stdout", "--show-lines", "--no-tokens"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("FILE: main.rs (2 lines)"))
+        .stderr(predicate::str::contains("Total lines: 2 (1 files"));
+
+    Ok(())
+}
+
+#[test]
+fn test_fail_over_tokens() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::write(root.join("main.rs"), "fn main() { println!(\"hello\"); }")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["-o", "digest.txt", "--fail-over-tokens", "10"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("allowed by --fail-over-tokens"));
+    // Still written for inspection
+    assert!(fs::read_to_string(root.join("digest.txt"))?.contains("fn main()"));
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["-o", "digest.txt", "--fail-over-tokens", "100000"]);
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn test_include_empty() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = TempDir::new()?;
+    let root = temp.path();
+    fs::write(root.join("empty.txt"), "")?;
+    fs::write(root.join("main.rs"), "fn main() {}")?;
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root).args(["--stdout", "--no-tokens"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("main.rs"))
+        .stdout(predicate::str::contains("empty.txt").not());
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_gitmelt"));
+    cmd.current_dir(root)
+        .args(["--stdout", "--no-tokens", "--include-empty"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("FILE: empty.txt"));
+
+    Ok(())
+}